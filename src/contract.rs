@@ -1,21 +1,26 @@
 use crate::error::BidError;
 use crate::msg::{BidExecuteMsg, BidInstantiateMsg, BidMigrateMsg, BidQueryMsg};
-#[cfg(not(feature = "library"))]
+#[cfg(not(any(feature = "library", feature = "factory")))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult};
 use cw2::set_contract_version;
 
 mod execute;
+mod helpers;
 mod instantiate;
+mod migrate;
 mod query;
+mod reply;
 use crate::contract::execute::_execute;
 use crate::contract::instantiate::_instantiate;
+use crate::contract::migrate::_migrate;
 use crate::contract::query::_query;
+use crate::contract::reply::_reply;
 
 const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-#[cfg_attr(not(feature = "library"), entry_point)]
+#[cfg_attr(not(any(feature = "library", feature = "factory")), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     env: Env,
@@ -26,7 +31,7 @@ pub fn instantiate(
     _instantiate(deps, env, info, msg)
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
+#[cfg_attr(not(any(feature = "library", feature = "factory")), entry_point)]
 pub fn execute(
     deps: DepsMut,
     env: Env,
@@ -36,12 +41,17 @@ pub fn execute(
     _execute(deps, env, info, msg)
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
+#[cfg_attr(not(any(feature = "library", feature = "factory")), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: BidQueryMsg) -> StdResult<Binary> {
     _query(deps, env, msg)
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: BidMigrateMsg) -> Result<Response, BidError> {
-    Ok(Response::default())
+#[cfg_attr(not(any(feature = "library", feature = "factory")), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, BidError> {
+    _reply(deps, env, msg)
+}
+
+#[cfg_attr(not(any(feature = "library", feature = "factory")), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, msg: BidMigrateMsg) -> Result<Response, BidError> {
+    _migrate(deps, env, msg)
 }
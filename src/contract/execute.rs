@@ -1,186 +1,1746 @@
+use crate::contract::helpers::{
+    accepted_funds, apply_config_update, apply_pending_changes, bid_to_beat, commission_msgs,
+    compute_clearing, count_refunds, ensure_can_bid, ensure_lot_deposited, ensure_not_paused,
+    ensure_owner, ensure_role, extend_deadline, factory_msgs, is_refundable, load_for_bid,
+    lot_msgs, next_bid_sequence, pause_lapsed, push_back, record_lot_deposit, save_bidder,
+    send_msg, top_bidders, update_phase, Funds,
+};
+use crate::contract::instantiate::create_auction;
 use crate::error::BidError;
-use crate::msg::BidExecuteMsg;
-use crate::state::{HighestBid, BIDDERS, BID_WINNER, CONFIG, HIGHEST_BID, IS_BIDDING_CLOSED};
-use cosmwasm_std::{coin, Addr, BankMsg, Coin, DepsMut, Env, MessageInfo, Response, Uint128};
+use crate::msg::{commitment_hash, BidExecuteMsg, BidReceiveMsg, ConfigUpdate};
+use crate::state::{
+    bidders, AuctionMode, AuctionOutcome, BatchBid, Bidder, Config, HighestBid, Lot, Ownership,
+    PendingConfigChange, Phase, Role, AUCTION_OUTCOME, BATCH_BIDS, BIDDER_COUNT, COMMITMENTS,
+    CONFIG, HIGHEST_BID, LOT_DEPOSITED, NEXT_BATCH_BID_ID, NEXT_CONFIG_CHANGE_ID, OWNERSHIP,
+    PAUSED, PENDING_CONFIG_CHANGES, PHASE, REFUND_CURSOR, ROLES, RUNNER_UP_BID, UNREVEALED_BIDDERS,
+    UNREVEALED_DEPOSITS,
+};
+use cosmwasm_std::{
+    from_binary, Addr, Binary, DepsMut, Empty, Env, MessageInfo, Order, Response, StdError,
+    StdResult, Storage, Uint128,
+};
+use cw20::Cw20ReceiveMsg;
+use cw721::Cw721ReceiveMsg;
+use cw_storage_plus::Bound;
+use cw_utils::Expiration;
 
 pub fn _execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: BidExecuteMsg,
 ) -> Result<Response, BidError> {
     match msg {
-        BidExecuteMsg::Bid {} => bid(deps, info),
-        BidExecuteMsg::Close {} => close(deps, info),
-        BidExecuteMsg::Retract { receiver } => retract(deps, info, receiver),
+        BidExecuteMsg::CreateAuction(msg) => create_auction(deps, env, info, *msg),
+        BidExecuteMsg::Bid { auction_id } => bid(
+            deps,
+            env,
+            auction_id,
+            info.sender,
+            Funds::Native(info.funds),
+        ),
+        BidExecuteMsg::Close { auction_id } => close(deps, env, info, auction_id),
+        BidExecuteMsg::Retract {
+            auction_id,
+            receiver,
+        } => retract(deps, info, auction_id, receiver),
+        BidExecuteMsg::UpdateFeeRecipients {
+            auction_id,
+            recipients,
+        } => update_config(
+            deps,
+            env,
+            info,
+            auction_id,
+            ConfigUpdate {
+                fee_recipients: Some(recipients),
+                ..ConfigUpdate::default()
+            },
+        ),
+        BidExecuteMsg::UpdateConfig { auction_id, update } => {
+            update_config(deps, env, info, auction_id, update)
+        }
+        BidExecuteMsg::CancelConfigChange {
+            auction_id,
+            change_id,
+        } => cancel_config_change(deps, info, auction_id, change_id),
+        BidExecuteMsg::CommitBid {
+            auction_id,
+            commitment,
+        } => commit_bid(
+            deps,
+            env,
+            auction_id,
+            info.sender,
+            Funds::Native(info.funds),
+            commitment,
+        ),
+        BidExecuteMsg::RevealBid {
+            auction_id,
+            amount,
+            salt,
+        } => reveal_bid(deps, env, info, auction_id, amount, salt),
+        BidExecuteMsg::BuyNow { auction_id } => buy_now(
+            deps,
+            env,
+            auction_id,
+            info.sender,
+            Funds::Native(info.funds),
+        ),
+        BidExecuteMsg::BatchBid {
+            auction_id,
+            quantity,
+            max_price,
+        } => batch_bid(
+            deps,
+            env,
+            auction_id,
+            info.sender,
+            Funds::Native(info.funds),
+            quantity,
+            max_price,
+        ),
+        BidExecuteMsg::Offer { auction_id, price } => offer(
+            deps,
+            env,
+            auction_id,
+            info.sender,
+            Funds::Native(info.funds),
+            price,
+        ),
+        BidExecuteMsg::Withdraw { auction_id } => withdraw(deps, env, info, auction_id),
+        BidExecuteMsg::ProcessRefunds { auction_id, limit } => {
+            process_refunds(deps, auction_id, limit)
+        }
+        BidExecuteMsg::ProxyBid { auction_id, max } => proxy_bid(
+            deps,
+            env,
+            auction_id,
+            info.sender,
+            Funds::Native(info.funds),
+            max,
+        ),
+        BidExecuteMsg::TransferOwnership {
+            auction_id,
+            new_owner,
+            expiry,
+        } => transfer_ownership(deps, env, info, auction_id, new_owner, expiry),
+        BidExecuteMsg::AcceptOwnership { auction_id } => {
+            accept_ownership(deps, env, info, auction_id)
+        }
+        BidExecuteMsg::RenounceOwnership { auction_id } => {
+            renounce_ownership(deps, info, auction_id)
+        }
+        BidExecuteMsg::GrantRole {
+            auction_id,
+            role,
+            address,
+        } => update_role(deps, info, auction_id, role, address, true),
+        BidExecuteMsg::RevokeRole {
+            auction_id,
+            role,
+            address,
+        } => update_role(deps, info, auction_id, role, address, false),
+        BidExecuteMsg::Pause { auction_id } => set_paused(deps, env, info, auction_id, true),
+        BidExecuteMsg::Resume { auction_id } => set_paused(deps, env, info, auction_id, false),
+        BidExecuteMsg::DepositLot { auction_id } => deposit_lot(deps, info, auction_id),
+        BidExecuteMsg::ReceiveNft(msg) => receive_nft(deps, info, msg),
+        BidExecuteMsg::Receive(msg) => receive(deps, env, info, msg),
     }
 }
 
-pub fn bid(deps: DepsMut, info: MessageInfo) -> Result<Response, BidError> {
-    // Can not bid if bidding close
-    if IS_BIDDING_CLOSED.load(deps.storage)? {
-        return Err(BidError::BiddingClosed {});
+// Bids or lot paid with a cw20 token, the bidder is the sender of the transfer
+pub fn receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: Cw20ReceiveMsg,
+) -> Result<Response, BidError> {
+    let sender = deps.api.addr_validate(&msg.sender)?;
+    let funds = Funds::Cw20 {
+        token: info.sender,
+        amount: msg.amount,
+    };
+    match from_binary(&msg.msg)? {
+        BidReceiveMsg::Bid { auction_id } => bid(deps, env, auction_id, sender, funds),
+        BidReceiveMsg::CommitBid {
+            auction_id,
+            commitment,
+        } => commit_bid(deps, env, auction_id, sender, funds, commitment),
+        BidReceiveMsg::BuyNow { auction_id } => buy_now(deps, env, auction_id, sender, funds),
+        BidReceiveMsg::BatchBid {
+            auction_id,
+            quantity,
+            max_price,
+        } => batch_bid(deps, env, auction_id, sender, funds, quantity, max_price),
+        BidReceiveMsg::Offer { auction_id, price } => {
+            offer(deps, env, auction_id, sender, funds, price)
+        }
+        BidReceiveMsg::ProxyBid { auction_id, max } => {
+            proxy_bid(deps, env, auction_id, sender, funds, max)
+        }
+        BidReceiveMsg::DepositLot { auction_id } => {
+            deposit_cw20_lot(deps, auction_id, sender, funds)
+        }
     }
+}
 
-    //Can not bid if owner
-    if info.sender == CONFIG.load(deps.storage)?.owner {
-        return Err(BidError::OwnerCantBid {});
+pub fn bid(
+    deps: DepsMut,
+    env: Env,
+    auction_id: u64,
+    sender: Addr,
+    funds: Funds,
+) -> Result<Response, BidError> {
+    // Can not bid outside of the bidding window, or if bidding close
+    let (config, config_events) =
+        load_for_bid(deps.storage, deps.api, &env.block, auction_id, &sender)?;
+
+    if let AuctionMode::Dutch { .. } | AuctionMode::Uniform {} | AuctionMode::Reverse { .. } =
+        config.mode
+    {
+        return Err(BidError::WrongAuctionMode {});
     }
 
-    let accepted_denom = CONFIG.load(deps.storage)?.accepted_token.denom;
+    let mut current_bid = accepted_funds(&config, &funds)?;
+    let opt_sender_bid = bidders().may_load(deps.storage, (auction_id, sender.clone()))?;
+    let previous_bid = opt_sender_bid
+        .as_ref()
+        .map(|sender_bid| sender_bid.amount)
+        .unwrap_or_default();
+    if matches!(&opt_sender_bid, Some(sender_bid) if sender_bid.proxy_bid.is_some()) {
+        return Err(BidError::ProxyBidActive {});
+    }
+    let highest_bid = HIGHEST_BID.load(deps.storage, auction_id)?;
 
-    // If fund do not have valid denom, error
-    if !info.funds.iter().any(|coin| coin.denom == accepted_denom) {
-        return Err(BidError::WrongToken {});
+    // A bid reaching the buy-now price only keeps what is needed to reach it
+    let buy_now_price = config
+        .active_buy_now_price(highest_bid.bid)
+        .filter(|price| previous_bid + current_bid >= *price);
+    let mut excess = Uint128::zero();
+    if let Some(price) = buy_now_price {
+        excess = previous_bid + current_bid - price;
+        current_bid -= excess;
     }
 
     // Prepare commission
-    let config = CONFIG.load(deps.storage)?;
-
-    let current_bid = info
-        .funds
-        .iter()
-        .find(|c| c.denom == accepted_denom)
-        .map(|m| m.amount)
-        .unwrap_or_else(|| Uint128::from(0u128));
-
-    if current_bid < config.commission {
+    let commission = config.commission.on_bid(current_bid);
+    if current_bid < commission {
         return Err(BidError::BidUnderCommission {});
     }
 
-    let commission_msg = BankMsg::Send {
-        to_address: CONFIG.load(deps.storage)?.owner.to_string(),
-        amount: vec![coin(
-            u128::from(config.commission),
-            config.accepted_token.denom,
-        )],
-    };
+    let mut response = Response::new().add_events(config_events);
+
+    if !commission.is_zero() {
+        response = response.add_messages(commission_msgs(&config, commission)?);
+    }
 
     // if total bid of user < Max bid, fail
 
-    let mut total_current_bid = current_bid;
-    let opt_sender_bid = BIDDERS.may_load(deps.storage, info.sender.clone())?;
-    if let Some(ref sender_bid) = opt_sender_bid {
-        total_current_bid = current_bid + sender_bid.0.amount;
-    };
+    let total_current_bid = previous_bid + current_bid;
 
-    let highest_bid = HIGHEST_BID.load(deps.storage)?;
+    // Multi-winner mode : a bid only has to outrank the lowest winning one
+    let bid_to_beat = bid_to_beat(deps.storage, auction_id, &config, Some(&sender))?;
+    let minimum_next_bid = config.minimum_next_bid(bid_to_beat);
 
-    if highest_bid.bid >= total_current_bid {
+    if bid_to_beat >= total_current_bid {
         return Err(BidError::BidTooLow {});
+    } else if total_current_bid < minimum_next_bid && buy_now_price.is_none() {
+        return Err(BidError::BidUnderMinimumIncrement {
+            minimum: minimum_next_bid,
+        });
     } else {
-        // It is a new highest bid. We need to update
+        // A new highest bid close to the deadline pushes it back
+        if let Some(new_end) = extend_deadline(deps.storage, auction_id, &config, &env.block)? {
+            response = response
+                .add_attribute("Bidding extended", "OK")
+                .add_attribute("New end", new_end.to_string());
+        }
 
-        HIGHEST_BID.save(
+        // It is a new highest bid. We need to update, unless a proxy bid defends the lead
+        if total_current_bid > highest_bid.bid
+            && challenge_highest_bid(
+                deps.storage,
+                auction_id,
+                &config,
+                &highest_bid,
+                &sender,
+                total_current_bid,
+                false,
+            )?
+            .is_none()
+        {
+            response = response.add_attribute("Outbid by proxy bid", "OK");
+        }
+
+        // New bidder starts from zero, ancient bidder adds to his amount
+        let mut bidder = opt_sender_bid.unwrap_or_default();
+        bidder.amount += current_bid;
+        bidder.nb_bids += 1;
+        bidder.commission_paid += commission;
+        bidder.sequence = next_bid_sequence(deps.storage, auction_id)?;
+
+        save_bidder(deps.storage, auction_id, &sender, &bidder)?;
+    }
+
+    response = response.add_attribute("Execute bid", "OK");
+
+    // Buy-now price reached, the auction ends without waiting for the owner
+    if let Some(price) = buy_now_price {
+        PHASE.save(deps.storage, auction_id, &Phase::Closed {})?;
+        AUCTION_OUTCOME.save(
             deps.storage,
-            &HighestBid {
-                address: info.sender.clone(),
-                bid: total_current_bid,
+            auction_id,
+            &AuctionOutcome::Sold {
+                winner: sender.clone(),
             },
         )?;
 
-        // New bidder
-        if opt_sender_bid.is_none() {
-            BIDDERS.save(
+        response = response
+            .add_attribute("Buy now", "OK")
+            .add_submessages(factory_msgs(&config, auction_id)?);
+
+        if !excess.is_zero() {
+            let msg_refund_excess = send_msg(&config, &sender, excess)?;
+            response = response
+                .add_attribute("Refund", excess.to_string())
+                .add_message(msg_refund_excess);
+        }
+
+        return settle_sale(deps.storage, auction_id, &config, &sender, price, response);
+    }
+
+    Ok(response)
+}
+
+pub fn close(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    auction_id: u64,
+) -> Result<Response, BidError> {
+    // Owner can close an open auction at any time, anyone can close once the deadline has passed
+    let mut config = CONFIG.load(deps.storage, auction_id)?;
+    let config_events =
+        apply_pending_changes(deps.storage, deps.api, &env.block, auction_id, &mut config)?;
+    match update_phase(deps.storage, auction_id, &config, &env.block)? {
+        Phase::Closed {} => return Err(BidError::BiddingClosed {}),
+        // The clock is stopped while paused, until the pause lapses
+        Phase::Ended {}
+            if PAUSED
+                .may_load(deps.storage, auction_id)?
+                .map_or(false, |paused_at| !pause_lapsed(&paused_at, &env.block)) =>
+        {
+            ensure_role(
                 deps.storage,
-                info.sender,
-                &(coin(u128::from(current_bid), accepted_denom), 1),
-            )?;
-        } else {
-            // Ancient bidder, we need to update the amount
-            BIDDERS.update(
+                auction_id,
+                &config,
+                &info.sender,
+                Role::Operator {},
+            )?
+        }
+        Phase::Ended {} => {}
+        _ => {
+            ensure_role(
                 deps.storage,
-                info.sender,
-                |x: Option<(Coin, u32)>| -> Result<(Coin, u32), BidError> {
-                    let mut last_bid: (Coin, u32) = x.unwrap();
-                    last_bid.0.amount += current_bid;
-                    last_bid.1 += 1;
-                    Ok(last_bid)
+                auction_id,
+                &config,
+                &info.sender,
+                Role::Operator {},
+            )?;
+            // Hidden bids can not be cut short
+            if let AuctionMode::Sealed { .. } = config.mode {
+                return Err(BidError::BiddingNotOver {});
+            }
+        }
+    }
+
+    let highest_bid = HIGHEST_BID.load(deps.storage, auction_id)?;
+
+    PHASE.save(deps.storage, auction_id, &Phase::Closed {})?;
+
+    let mut response = Response::new()
+        .add_events(config_events)
+        .add_submessages(factory_msgs(&config, auction_id)?);
+
+    // Deposits never revealed are kept as commission
+    if let AuctionMode::Sealed {
+        slash_unrevealed: true,
+        ..
+    } = config.mode
+    {
+        let unrevealed_deposits = UNREVEALED_DEPOSITS.load(deps.storage, auction_id)?;
+        if !unrevealed_deposits.is_zero() {
+            response = response
+                .add_attribute("Slashed deposits", unrevealed_deposits.to_string())
+                .add_messages(commission_msgs(&config, unrevealed_deposits)?);
+        }
+    }
+
+    if config.quantity > 1 {
+        return settle_many_sales(deps.storage, auction_id, &config, response);
+    }
+    if config.mode == (AuctionMode::Uniform {}) {
+        return settle_clearing(deps.storage, auction_id, &config, response);
+    }
+    if let AuctionMode::Reverse { .. } = config.mode {
+        return settle_reverse(deps.storage, auction_id, &config, response);
+    }
+
+    // Under the reserve price nothing is sold and every bidder can retract,
+    // a dutch auction still open at close found no buyer
+    let under_reserve_price =
+        matches!(config.reserve_price, Some(reserve_price) if highest_bid.bid < reserve_price);
+    if under_reserve_price || matches!(config.mode, AuctionMode::Dutch { .. }) {
+        AUCTION_OUTCOME.save(deps.storage, auction_id, &AuctionOutcome::NoSale {})?;
+
+        // Unsold lot goes back to the owner
+        return Ok(response
+            .add_attribute("Execute close without sale", "OK")
+            .add_messages(lot_msgs(deps.storage, auction_id, &config, &config.owner)?));
+    }
+
+    // Nobody bid, the highest bid still points to the instantiator
+    if highest_bid.bid.is_zero() {
+        AUCTION_OUTCOME.save(deps.storage, auction_id, &AuctionOutcome::NoSale {})?;
+
+        return Ok(response
+            .add_attribute("Execute close without funds to owner", "OK")
+            .add_messages(lot_msgs(deps.storage, auction_id, &config, &config.owner)?));
+    }
+
+    AUCTION_OUTCOME.save(
+        deps.storage,
+        auction_id,
+        &AuctionOutcome::Sold {
+            winner: highest_bid.address.clone(),
+        },
+    )?;
+
+    let price = config.settlement_price(
+        highest_bid.bid,
+        RUNNER_UP_BID.load(deps.storage, auction_id)?,
+    );
+    settle_sale(
+        deps.storage,
+        auction_id,
+        &config,
+        &highest_bid.address,
+        price,
+        response,
+    )
+}
+
+// A challenger ready to bid up to `challenger_max` meets the highest bid. A proxy bidder in the lead
+// keeps it as long as his escrow covers the challenge, proxy bids are only raised to what is
+// needed to lead. Returns the visible bid of the challenger when he takes the lead
+fn challenge_highest_bid(
+    storage: &mut dyn Storage,
+    auction_id: u64,
+    config: &Config,
+    highest_bid: &HighestBid,
+    challenger: &Addr,
+    challenger_max: Uint128,
+    challenger_is_proxy: bool,
+) -> StdResult<Option<Uint128>> {
+    let mut incumbent = None;
+    if highest_bid.address != *challenger {
+        incumbent = bidders()
+            .may_load(storage, (auction_id, highest_bid.address.clone()))?
+            .filter(|bidder| bidder.proxy_bid.is_some());
+    }
+    let incumbent_max = incumbent
+        .as_ref()
+        .map(|bidder| bidder.amount)
+        .unwrap_or(highest_bid.bid);
+
+    if let Some(mut incumbent) = incumbent {
+        if incumbent_max >= challenger_max {
+            let visible = config.proxy_visible_bid(incumbent_max, challenger_max);
+            incumbent.proxy_bid = Some(visible);
+            bidders().save(
+                storage,
+                (auction_id, highest_bid.address.clone()),
+                &incumbent,
+            )?;
+            HIGHEST_BID.save(
+                storage,
+                auction_id,
+                &HighestBid {
+                    address: highest_bid.address.clone(),
+                    bid: visible,
                 },
             )?;
+            RUNNER_UP_BID.save(storage, auction_id, &challenger_max)?;
+            return Ok(None);
         }
+
+        // Outbid, the whole escrow of the proxy bidder is now visible
+        incumbent.proxy_bid = Some(incumbent_max);
+        bidders().save(
+            storage,
+            (auction_id, highest_bid.address.clone()),
+            &incumbent,
+        )?;
     }
 
-    Ok(Response::new()
-        .add_attribute("Execute bid", "OK")
-        .add_message(commission_msg))
+    let visible = if challenger_is_proxy {
+        config.proxy_visible_bid(challenger_max, incumbent_max)
+    } else {
+        challenger_max
+    };
+
+    // Previous highest bid becomes the runner-up, unless the highest bidder raised his own
+    if highest_bid.address != *challenger {
+        RUNNER_UP_BID.save(storage, auction_id, &incumbent_max)?;
+    }
+    HIGHEST_BID.save(
+        storage,
+        auction_id,
+        &HighestBid {
+            address: challenger.clone(),
+            bid: visible,
+        },
+    )?;
+    Ok(Some(visible))
 }
 
-pub fn close(deps: DepsMut, info: MessageInfo) -> Result<Response, BidError> {
-    let config = CONFIG.load(deps.storage)?;
-    if info.sender != config.owner {
-        return Err(BidError::Unauthorized {});
+// Pays the owner for the lot, the winner keeps whatever he escrowed over the price
+fn settle_sale(
+    storage: &mut dyn Storage,
+    auction_id: u64,
+    config: &Config,
+    winner_address: &Addr,
+    price: Uint128,
+    mut response: Response,
+) -> Result<Response, BidError> {
+    let (price, commission, amount_to_send) =
+        charge_winner(storage, auction_id, config, winner_address, price)?;
+
+    response = response
+        .add_attribute("Execute close with funds to owner", "OK")
+        .add_attribute("Bid winner", winner_address.to_string())
+        .add_attribute("Price", price.to_string());
+
+    if !commission.is_zero() {
+        response = response
+            .add_attribute("Commission", commission.to_string())
+            .add_messages(commission_msgs(config, commission)?);
     }
 
-    let highest_bid = HIGHEST_BID.load(deps.storage)?;
-    BID_WINNER.save(deps.storage, &highest_bid.address)?;
+    if !amount_to_send.is_zero() {
+        let msg_send_highest_bid_to_owner = send_msg(config, &config.owner, amount_to_send)?;
+        response = response.add_message(msg_send_highest_bid_to_owner);
+    }
 
-    IS_BIDDING_CLOSED.save(deps.storage, &true)?;
+    Ok(response.add_messages(lot_msgs(storage, auction_id, config, winner_address)?))
+}
 
-    if highest_bid.bid > Uint128::zero() {
-        let nb_bid = Uint128::from(
-            BIDDERS
-                .load(deps.storage, highest_bid.address.clone())
-                .unwrap()
-                .1,
-        );
-        let amount_to_send = highest_bid.bid - nb_bid * config.commission;
+// Multi-winner mode : each of the top bidders over the reserve price buys one item at his own bid,
+// the owner gets the combined amount
+fn settle_many_sales(
+    storage: &mut dyn Storage,
+    auction_id: u64,
+    config: &Config,
+    mut response: Response,
+) -> Result<Response, BidError> {
+    let reserve_price = config.reserve_price.unwrap_or_default();
+    let winners: Vec<(Addr, Bidder)> = top_bidders(storage, auction_id, config.quantity as usize)?
+        .into_iter()
+        .filter(|(_, bidder)| bidder.amount >= reserve_price)
+        .collect();
 
-        let msg_send_highest_bid_to_owner = BankMsg::Send {
-            to_address: config.owner.to_string(),
-            amount: vec![coin(
-                u128::from(amount_to_send),
-                config.accepted_token.denom,
-            )],
-        };
+    if winners.is_empty() {
+        AUCTION_OUTCOME.save(storage, auction_id, &AuctionOutcome::NoSale {})?;
+        return Ok(response.add_attribute("Execute close without sale", "OK"));
+    }
+
+    response = response.add_attribute("Execute close with funds to owner", "OK");
+
+    let mut total_commission = Uint128::zero();
+    let mut total_to_send = Uint128::zero();
+    for (winner_address, winner) in &winners {
+        let (price, commission, amount_to_send) =
+            charge_winner(storage, auction_id, config, winner_address, winner.amount)?;
+        total_commission += commission;
+        total_to_send += amount_to_send;
+        response = response
+            .add_attribute("Bid winner", winner_address.to_string())
+            .add_attribute("Price", price.to_string());
+    }
+
+    AUCTION_OUTCOME.save(
+        storage,
+        auction_id,
+        &AuctionOutcome::SoldToMany {
+            winners: winners.into_iter().map(|(address, _)| address).collect(),
+        },
+    )?;
+
+    if !total_commission.is_zero() {
+        response = response
+            .add_attribute("Commission", total_commission.to_string())
+            .add_messages(commission_msgs(config, total_commission)?);
+    }
 
-        return Ok(Response::new()
-            .add_attribute("Execute close with funds to owner", "OK")
-            .add_attribute("Bid winner", highest_bid.address.to_string())
-            .add_message(msg_send_highest_bid_to_owner));
+    if !total_to_send.is_zero() {
+        response = response.add_message(send_msg(config, &config.owner, total_to_send)?);
     }
 
-    Ok(Response::new().add_attribute("Execute close without funds to owner", "OK"))
+    Ok(response)
+}
+
+// Uniform mode : every winner pays the clearing price for his units and receives them, unsold
+// units go back to the owner
+fn settle_clearing(
+    storage: &mut dyn Storage,
+    auction_id: u64,
+    config: &Config,
+    mut response: Response,
+) -> Result<Response, BidError> {
+    let lot = config.lot.clone().ok_or(BidError::InvalidLot {})?;
+    let clearing = compute_clearing(storage, auction_id, lot.units().unwrap_or_default())?;
+
+    if clearing.fills.is_empty() {
+        AUCTION_OUTCOME.save(storage, auction_id, &AuctionOutcome::NoSale {})?;
+        return Ok(response
+            .add_attribute("Execute close without sale", "OK")
+            .add_messages(lot_msgs(storage, auction_id, config, &config.owner)?));
+    }
+
+    LOT_DEPOSITED.save(storage, auction_id, &false)?;
+    response = response
+        .add_attribute("Execute close with funds to owner", "OK")
+        .add_attribute("Clearing price", clearing.price.to_string());
+
+    let mut total_commission = Uint128::zero();
+    let mut total_to_send = Uint128::zero();
+    for (winner_address, units) in &clearing.fills {
+        let price = units.checked_mul(clearing.price).map_err(StdError::from)?;
+        let (price, commission, amount_to_send) =
+            charge_winner(storage, auction_id, config, winner_address, price)?;
+        total_commission += commission;
+        total_to_send += amount_to_send;
+
+        let units_lot = lot.with_units(*units).ok_or(BidError::InvalidLot {})?;
+        response = response
+            .add_attribute("Bid winner", winner_address.to_string())
+            .add_attribute("Price", price.to_string())
+            .add_message(units_lot.transfer_msg(winner_address)?);
+    }
+
+    AUCTION_OUTCOME.save(
+        storage,
+        auction_id,
+        &AuctionOutcome::SoldToMany {
+            winners: clearing
+                .fills
+                .into_iter()
+                .map(|(address, _)| address)
+                .collect(),
+        },
+    )?;
+
+    if clearing.sold < clearing.supply {
+        let unsold = lot
+            .with_units(clearing.supply - clearing.sold)
+            .ok_or(BidError::InvalidLot {})?;
+        response = response.add_message(unsold.transfer_msg(&config.owner)?);
+    }
+
+    if !total_commission.is_zero() {
+        response = response
+            .add_attribute("Commission", total_commission.to_string())
+            .add_messages(commission_msgs(config, total_commission)?);
+    }
+
+    if !total_to_send.is_zero() {
+        response = response.add_message(send_msg(config, &config.owner, total_to_send)?);
+    }
+
+    Ok(response)
+}
+
+// Reverse mode : the best offer is paid from the budget, what is left of it goes back to the owner.
+// Bonds stay retractable by every supplier
+fn settle_reverse(
+    storage: &mut dyn Storage,
+    auction_id: u64,
+    config: &Config,
+    mut response: Response,
+) -> Result<Response, BidError> {
+    let best_offer = HIGHEST_BID.load(storage, auction_id)?;
+    if best_offer.bid.is_zero() {
+        AUCTION_OUTCOME.save(storage, auction_id, &AuctionOutcome::NoSale {})?;
+        return Ok(response
+            .add_attribute("Execute close without sale", "OK")
+            .add_messages(lot_msgs(storage, auction_id, config, &config.owner)?));
+    }
+
+    AUCTION_OUTCOME.save(
+        storage,
+        auction_id,
+        &AuctionOutcome::Sold {
+            winner: best_offer.address.clone(),
+        },
+    )?;
+    LOT_DEPOSITED.save(storage, auction_id, &false)?;
+
+    let price = best_offer.bid;
+    let budget = config.lot.as_ref().and_then(Lot::units).unwrap_or_default();
+
+    // Commission is only taken once, on the winning offer
+    let commission =
+        (config.commission.on_bid(price) + config.commission.on_winning_bid(price)).min(price);
+
+    response = response
+        .add_attribute("Execute close with payment to supplier", "OK")
+        .add_attribute("Bid winner", best_offer.address.to_string())
+        .add_attribute("Price", price.to_string());
+
+    if !commission.is_zero() {
+        response = response
+            .add_attribute("Commission", commission.to_string())
+            .add_messages(commission_msgs(config, commission)?);
+    }
+
+    if price > commission {
+        response = response.add_message(send_msg(config, &best_offer.address, price - commission)?);
+    }
+
+    if budget > price {
+        response = response.add_message(send_msg(config, &config.owner, budget - price)?);
+    }
+
+    Ok(response)
+}
+
+// Takes the price out of the winner escrow, returns the price actually paid, the commission
+// on it and what goes to the owner
+fn charge_winner(
+    storage: &mut dyn Storage,
+    auction_id: u64,
+    config: &Config,
+    winner_address: &Addr,
+    price: Uint128,
+) -> StdResult<(Uint128, Uint128, Uint128)> {
+    let mut winner = bidders().load(storage, (auction_id, winner_address.clone()))?;
+
+    // Commission already paid by the winner is never given back
+    let price = price.max(winner.commission_paid);
+
+    // Commission already paid on each bid is not sent twice
+    let commission = config.commission.on_winning_bid(price);
+    let amount_to_send = price - winner.commission_paid - commission;
+
+    // Whatever the winner escrowed over the price stays retractable
+    winner.amount -= price;
+    winner.commission_paid = Uint128::zero();
+    bidders().save(storage, (auction_id, winner_address.clone()), &winner)?;
+
+    Ok((price, commission, amount_to_send))
 }
 
 pub fn retract(
     deps: DepsMut,
     info: MessageInfo,
+    auction_id: u64,
     receiver: Option<Addr>,
 ) -> Result<Response, BidError> {
-    if !IS_BIDDING_CLOSED.load(deps.storage)? {
+    if PHASE.load(deps.storage, auction_id)? != (Phase::Closed {}) {
         return Err(BidError::BiddingNotClose {});
     }
 
-    let opt_bidder_coin = BIDDERS.may_load(deps.storage, info.sender.clone())?;
-    if opt_bidder_coin.is_none() {
-        return Err(BidError::NothingToRetract {});
-    }
+    let bid = bidders()
+        .may_load(deps.storage, (auction_id, info.sender.clone()))?
+        .ok_or(BidError::NothingToRetract {})?;
+
+    let config = CONFIG.load(deps.storage, auction_id)?;
 
-    let highest_bidder = HIGHEST_BID.load(deps.storage)?.address;
-    if highest_bidder == info.sender {
+    let token_to_send = bid.amount - bid.commission_paid;
+
+    // Winner can only retract what he escrowed over his bid
+    let is_winner = AUCTION_OUTCOME
+        .load(deps.storage, auction_id)?
+        .is_winner(&info.sender);
+    if is_winner && token_to_send.is_zero() {
         return Err(BidError::WinnerCantRetract {});
     }
 
-    let config = CONFIG.load(deps.storage).unwrap();
-
-    let bid = opt_bidder_coin.unwrap();
-    let token_to_send = bid.0.amount - Uint128::from(bid.1) * config.commission;
+    if let AuctionMode::Sealed {
+        slash_unrevealed: true,
+        ..
+    } = config.mode
+    {
+        if COMMITMENTS.has(deps.storage, (auction_id, info.sender.clone())) {
+            return Err(BidError::DepositSlashed {});
+        }
+    }
 
     let mut token_receiver = info.sender.clone();
     if let Some(new_token_receiver) = receiver {
         token_receiver = new_token_receiver;
     }
 
-    let msg_send_retract = BankMsg::Send {
-        to_address: token_receiver.to_string(),
-        amount: vec![coin(u128::from(token_to_send), config.accepted_token.denom)],
-    };
+    // Losers the batch refund has not reached yet are no longer pending
+    let reached = REFUND_CURSOR
+        .may_load(deps.storage, auction_id)?
+        .map_or(false, |cursor| info.sender <= cursor);
+    if !is_winner && !reached {
+        count_refunds(deps.storage, auction_id, 1)?;
+    }
+
+    let msg_send_retract = send_msg(&config, &token_receiver, token_to_send)?;
 
-    BIDDERS.remove(deps.storage, info.sender.clone());
+    bidders().remove(deps.storage, (auction_id, info.sender.clone()))?;
 
     Ok(Response::new()
         .add_attribute("Execute retract", "OK")
         .add_attribute("Address calling", info.sender.to_string())
         .add_message(msg_send_retract))
 }
+
+const DEFAULT_REFUND_LIMIT: u32 = 10;
+const MAX_REFUND_LIMIT: u32 = 30;
+
+// Anyone can push the escrow back to the losers, `limit` bidders at a time from where the last
+// call stopped
+pub fn process_refunds(
+    deps: DepsMut,
+    auction_id: u64,
+    limit: Option<u32>,
+) -> Result<Response, BidError> {
+    if PHASE.load(deps.storage, auction_id)? != (Phase::Closed {}) {
+        return Err(BidError::BiddingNotClose {});
+    }
+
+    let config = CONFIG.load(deps.storage, auction_id)?;
+    let outcome = AUCTION_OUTCOME.load(deps.storage, auction_id)?;
+    let limit = limit.unwrap_or(DEFAULT_REFUND_LIMIT).min(MAX_REFUND_LIMIT) as usize;
+    let start = REFUND_CURSOR
+        .may_load(deps.storage, auction_id)?
+        .map(Bound::exclusive);
+
+    let batch = bidders()
+        .prefix(auction_id)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    let last = match batch.last() {
+        Some((address, _)) => address.clone(),
+        None => return Err(BidError::NothingToRetract {}),
+    };
+
+    let mut refunded = 0;
+    let mut msgs = vec![];
+    for (address, bidder) in batch {
+        if !is_refundable(deps.storage, auction_id, &config, &outcome, &address) {
+            continue;
+        }
+        refunded += 1;
+        let amount = bidder.amount - bidder.commission_paid;
+        if !amount.is_zero() {
+            msgs.push(send_msg(&config, &address, amount)?);
+            bidders().remove(deps.storage, (auction_id, address))?;
+        }
+    }
+    REFUND_CURSOR.save(deps.storage, auction_id, &last)?;
+    count_refunds(deps.storage, auction_id, refunded)?;
+
+    Ok(Response::new()
+        .add_attribute("Execute process refunds", "OK")
+        .add_attribute("Refunds sent", msgs.len().to_string())
+        .add_messages(msgs))
+}
+
+// Early exit of a bidder who is not winning, the penalty is sent to the fee recipients
+pub fn withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    auction_id: u64,
+) -> Result<Response, BidError> {
+    let mut config = CONFIG.load(deps.storage, auction_id)?;
+    let config_events =
+        apply_pending_changes(deps.storage, deps.api, &env.block, auction_id, &mut config)?;
+    if update_phase(deps.storage, auction_id, &config, &env.block)? == (Phase::Closed {}) {
+        return Err(BidError::BiddingClosed {});
+    }
+    // Commitments, batch bids and dutch purchases can not be undone
+    if let AuctionMode::Sealed { .. } | AuctionMode::Dutch { .. } | AuctionMode::Uniform {} =
+        config.mode
+    {
+        return Err(BidError::WrongAuctionMode {});
+    }
+
+    let bidder = bidders()
+        .may_load(deps.storage, (auction_id, info.sender.clone()))?
+        .ok_or(BidError::NothingToRetract {})?;
+
+    let highest_bid = HIGHEST_BID.load(deps.storage, auction_id)?;
+    let is_winning = if config.quantity > 1 {
+        top_bidders(deps.storage, auction_id, config.quantity as usize)?
+            .iter()
+            .any(|(address, _)| *address == info.sender)
+    } else {
+        highest_bid.address == info.sender
+    };
+    if is_winning {
+        return Err(BidError::WinnerCantWithdraw {});
+    }
+
+    bidders().remove(deps.storage, (auction_id, info.sender.clone()))?;
+    BIDDER_COUNT.update(deps.storage, auction_id, |count| -> StdResult<_> {
+        Ok(count.unwrap_or_default() - 1)
+    })?;
+
+    // The runner-up may have been this bid. Bidders not in the lead always show their whole
+    // escrow, so the next one by amount replaces it
+    if config.mode == (AuctionMode::Open {}) && config.quantity == 1 {
+        let runner_up_bid = top_bidders(deps.storage, auction_id, 2)?
+            .into_iter()
+            .find(|(address, _)| *address != highest_bid.address)
+            .map(|(_, bidder)| bidder.amount)
+            .unwrap_or_default();
+        RUNNER_UP_BID.save(deps.storage, auction_id, &runner_up_bid)?;
+    }
+
+    let escrow = bidder.amount - bidder.commission_paid;
+    let penalty = config
+        .withdrawal_penalty
+        .map(|rate| escrow * rate)
+        .unwrap_or_default();
+
+    let mut response = Response::new()
+        .add_events(config_events)
+        .add_attribute("Execute withdraw", "OK")
+        .add_attribute("Address calling", info.sender.to_string());
+
+    if !penalty.is_zero() {
+        response = response
+            .add_attribute("Penalty", penalty.to_string())
+            .add_messages(commission_msgs(&config, penalty)?);
+    }
+
+    if escrow > penalty {
+        response = response.add_message(send_msg(&config, &info.sender, escrow - penalty)?);
+    }
+
+    Ok(response)
+}
+
+// A new offer replaces the pending one
+pub fn transfer_ownership(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    auction_id: u64,
+    new_owner: String,
+    expiry: Option<Expiration>,
+) -> Result<Response, BidError> {
+    let config = CONFIG.load(deps.storage, auction_id)?;
+    ensure_owner(deps.storage, auction_id, &config, &info.sender)?;
+
+    let new_owner = deps.api.addr_validate(&new_owner)?;
+    if expiry.map_or(false, |expiry| expiry.is_expired(&env.block)) {
+        return Err(BidError::OwnershipTransferExpired {});
+    }
+
+    OWNERSHIP.save(
+        deps.storage,
+        auction_id,
+        &Ownership {
+            pending_owner: Some(new_owner.clone()),
+            pending_expiry: expiry,
+            renounced: false,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("Execute transfer ownership", "OK")
+        .add_attribute("Pending owner", new_owner.to_string()))
+}
+
+pub fn accept_ownership(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    auction_id: u64,
+) -> Result<Response, BidError> {
+    let ownership = OWNERSHIP
+        .may_load(deps.storage, auction_id)?
+        .unwrap_or_default();
+    match ownership.pending_owner {
+        None => return Err(BidError::NoPendingOwnership {}),
+        Some(pending_owner) if pending_owner != info.sender => {
+            return Err(BidError::Unauthorized {})
+        }
+        _ => {}
+    }
+    if ownership
+        .pending_expiry
+        .map_or(false, |expiry| expiry.is_expired(&env.block))
+    {
+        return Err(BidError::OwnershipTransferExpired {});
+    }
+
+    // Owner can not bid on his own auction
+    if bidders().has(deps.storage, (auction_id, info.sender.clone()))
+        || COMMITMENTS.has(deps.storage, (auction_id, info.sender.clone()))
+    {
+        return Err(BidError::OwnerCantBid {});
+    }
+
+    let mut config = CONFIG.load(deps.storage, auction_id)?;
+    config.owner = info.sender.clone();
+    CONFIG.save(deps.storage, auction_id, &config)?;
+    OWNERSHIP.save(deps.storage, auction_id, &Ownership::default())?;
+
+    Ok(Response::new()
+        .add_attribute("Execute accept ownership", "OK")
+        .add_attribute("Address calling", info.sender.to_string()))
+}
+
+pub fn renounce_ownership(
+    deps: DepsMut,
+    info: MessageInfo,
+    auction_id: u64,
+) -> Result<Response, BidError> {
+    let config = CONFIG.load(deps.storage, auction_id)?;
+    ensure_owner(deps.storage, auction_id, &config, &info.sender)?;
+    // Nobody could close it any more
+    if config.end == (Expiration::Never {}) {
+        return Err(BidError::RenounceWithoutDeadline {});
+    }
+    // Nobody could resume it before the pause lapses
+    ensure_not_paused(deps.storage, auction_id)?;
+
+    OWNERSHIP.save(
+        deps.storage,
+        auction_id,
+        &Ownership {
+            renounced: true,
+            ..Ownership::default()
+        },
+    )?;
+
+    Ok(Response::new().add_attribute("Execute renounce ownership", "OK"))
+}
+
+const MAX_PENDING_CONFIG_CHANGES: u32 = 10;
+
+pub fn update_config(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    auction_id: u64,
+    update: ConfigUpdate,
+) -> Result<Response, BidError> {
+    let mut config = CONFIG.load(deps.storage, auction_id)?;
+    match update_phase(deps.storage, auction_id, &config, &env.block)? {
+        Phase::Closed {} => return Err(BidError::BiddingClosed {}),
+        Phase::Ended {} => return Err(BidError::BiddingExpired {}),
+        _ => {}
+    }
+
+    // Fee settings are left to the fee manager, the terms of the auction to the owner only
+    ensure_role(
+        deps.storage,
+        auction_id,
+        &config,
+        &info.sender,
+        Role::FeeManager {},
+    )?;
+    if update.changes_terms() {
+        ensure_owner(deps.storage, auction_id, &config, &info.sender)?;
+    } else if !update.changes_fees() {
+        return Err(BidError::EmptyConfigUpdate {});
+    }
+
+    // Checked now, and once more when the timelock has passed
+    let events = apply_config_update(
+        deps.storage,
+        deps.api,
+        &env.block,
+        auction_id,
+        &mut config,
+        update.clone(),
+    )?;
+
+    let timelock = match config.config_timelock {
+        Some(timelock) => timelock,
+        None => {
+            CONFIG.save(deps.storage, auction_id, &config)?;
+            return Ok(Response::new()
+                .add_attribute("Execute update config", "OK")
+                .add_events(events));
+        }
+    };
+
+    // Keeps the changes applied along a bid within gas limits
+    let pending = PENDING_CONFIG_CHANGES
+        .prefix(auction_id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .count();
+    if pending >= MAX_PENDING_CONFIG_CHANGES as usize {
+        return Err(BidError::TooManyPendingConfigChanges {
+            max: MAX_PENDING_CONFIG_CHANGES,
+        });
+    }
+
+    let id = NEXT_CONFIG_CHANGE_ID
+        .may_load(deps.storage, auction_id)?
+        .unwrap_or_default();
+    NEXT_CONFIG_CHANGE_ID.save(deps.storage, auction_id, &(id + 1))?;
+    let effective = timelock.after(&env.block);
+    PENDING_CONFIG_CHANGES.save(
+        deps.storage,
+        (auction_id, id),
+        &PendingConfigChange {
+            id,
+            update,
+            effective,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("Execute update config", "OK")
+        .add_attribute("Change id", id.to_string())
+        .add_attribute("Effective", effective.to_string()))
+}
+
+pub fn cancel_config_change(
+    deps: DepsMut,
+    info: MessageInfo,
+    auction_id: u64,
+    change_id: u64,
+) -> Result<Response, BidError> {
+    let config = CONFIG.load(deps.storage, auction_id)?;
+    ensure_owner(deps.storage, auction_id, &config, &info.sender)?;
+
+    if !PENDING_CONFIG_CHANGES.has(deps.storage, (auction_id, change_id)) {
+        return Err(BidError::ConfigChangeNotFound {});
+    }
+    PENDING_CONFIG_CHANGES.remove(deps.storage, (auction_id, change_id));
+
+    Ok(Response::new()
+        .add_attribute("Execute cancel config change", "OK")
+        .add_attribute("Change id", change_id.to_string()))
+}
+
+pub fn update_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    auction_id: u64,
+    role: Role,
+    address: String,
+    granted: bool,
+) -> Result<Response, BidError> {
+    let config = CONFIG.load(deps.storage, auction_id)?;
+    ensure_role(
+        deps.storage,
+        auction_id,
+        &config,
+        &info.sender,
+        Role::Admin {},
+    )?;
+
+    let address = deps.api.addr_validate(&address)?;
+    let key = (auction_id, role.key(), address.clone());
+    // Role holders can not bid
+    if granted
+        && (bidders().has(deps.storage, (auction_id, address.clone()))
+            || COMMITMENTS.has(deps.storage, (auction_id, address.clone())))
+    {
+        return Err(BidError::OwnerCantBid {});
+    }
+    if granted {
+        ROLES.save(deps.storage, key, &Empty {})?;
+    } else {
+        ROLES.remove(deps.storage, key);
+    }
+
+    let action = if granted {
+        "Execute grant role"
+    } else {
+        "Execute revoke role"
+    };
+    Ok(Response::new()
+        .add_attribute(action, "OK")
+        .add_attribute("Role", role.key())
+        .add_attribute("Address", address.to_string()))
+}
+
+pub fn set_paused(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    auction_id: u64,
+    paused: bool,
+) -> Result<Response, BidError> {
+    let mut config = CONFIG.load(deps.storage, auction_id)?;
+    let paused_at = PAUSED.may_load(deps.storage, auction_id)?;
+    let lapsed = paused_at
+        .as_ref()
+        .map_or(false, |paused_at| pause_lapsed(paused_at, &env.block));
+    if paused || !lapsed {
+        ensure_role(
+            deps.storage,
+            auction_id,
+            &config,
+            &info.sender,
+            Role::Operator {},
+        )?;
+    }
+    match update_phase(deps.storage, auction_id, &config, &env.block)? {
+        Phase::Closed {} => return Err(BidError::BiddingClosed {}),
+        // Only an operator could close it
+        Phase::Ended {} if paused => return Err(BidError::BiddingExpired {}),
+        _ => {}
+    }
+    // Hidden bids and falling prices run on the clock, they can not be suspended
+    if let AuctionMode::Sealed { .. } | AuctionMode::Dutch { .. } = config.mode {
+        return Err(BidError::WrongAuctionMode {});
+    }
+
+    if paused {
+        if paused_at.is_none() {
+            PAUSED.save(deps.storage, auction_id, &env.block)?;
+        }
+        return Ok(Response::new().add_attribute("Execute pause", "OK"));
+    }
+
+    // Bidders get back the time they could not bid
+    if let Some(paused_at) = paused_at {
+        config.end = push_back(config.end, &paused_at, &env.block);
+        CONFIG.save(deps.storage, auction_id, &config)?;
+        PAUSED.remove(deps.storage, auction_id);
+    }
+    Ok(Response::new()
+        .add_attribute("Execute resume", "OK")
+        .add_attribute("End", config.end.to_string()))
+}
+
+pub fn commit_bid(
+    deps: DepsMut,
+    env: Env,
+    auction_id: u64,
+    sender: Addr,
+    funds: Funds,
+    commitment: Binary,
+) -> Result<Response, BidError> {
+    let mut config = CONFIG.load(deps.storage, auction_id)?;
+    ensure_not_paused(deps.storage, auction_id)?;
+    let config_events =
+        apply_pending_changes(deps.storage, deps.api, &env.block, auction_id, &mut config)?;
+    match update_phase(deps.storage, auction_id, &config, &env.block)? {
+        Phase::Commit {} => {}
+        Phase::Bidding {} => return Err(BidError::WrongAuctionMode {}),
+        Phase::Closed {} => return Err(BidError::BiddingClosed {}),
+        _ => return Err(BidError::NotCommitPhase {}),
+    }
+
+    ensure_can_bid(deps.storage, auction_id, &config, &sender)?;
+    ensure_lot_deposited(deps.storage, auction_id, &config)?;
+
+    // Commission is taken on the deposit as the bid is still hidden
+    let deposit = accepted_funds(&config, &funds)?;
+    let commission = config.commission.on_bid(deposit);
+    if deposit < commission {
+        return Err(BidError::BidUnderCommission {});
+    }
+
+    // Committing again replaces the commitment and adds to the deposit
+    let mut bidder = bidders()
+        .may_load(deps.storage, (auction_id, sender.clone()))?
+        .unwrap_or_default();
+    bidder.amount += deposit;
+    bidder.nb_bids += 1;
+    bidder.commission_paid += commission;
+    bidder.sequence = next_bid_sequence(deps.storage, auction_id)?;
+
+    save_bidder(deps.storage, auction_id, &sender, &bidder)?;
+    if !COMMITMENTS.has(deps.storage, (auction_id, sender.clone())) {
+        UNREVEALED_BIDDERS.update(deps.storage, auction_id, |count| -> StdResult<_> {
+            Ok(count.unwrap_or_default() + 1)
+        })?;
+    }
+    COMMITMENTS.save(deps.storage, (auction_id, sender.clone()), &commitment)?;
+    UNREVEALED_DEPOSITS.update(
+        deps.storage,
+        auction_id,
+        |total| -> Result<Uint128, BidError> {
+            Ok(total.unwrap_or_default() + deposit - commission)
+        },
+    )?;
+
+    let mut response = Response::new().add_events(config_events);
+
+    if !commission.is_zero() {
+        response = response.add_messages(commission_msgs(&config, commission)?);
+    }
+
+    Ok(response
+        .add_attribute("Execute commit bid", "OK")
+        .add_attribute("Address calling", sender.to_string()))
+}
+
+pub fn reveal_bid(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    auction_id: u64,
+    amount: Uint128,
+    salt: String,
+) -> Result<Response, BidError> {
+    let config = CONFIG.load(deps.storage, auction_id)?;
+    match update_phase(deps.storage, auction_id, &config, &env.block)? {
+        Phase::Reveal {} => {}
+        Phase::Bidding {} => return Err(BidError::WrongAuctionMode {}),
+        Phase::Closed {} => return Err(BidError::BiddingClosed {}),
+        _ => return Err(BidError::NotRevealPhase {}),
+    }
+
+    let commitment = COMMITMENTS
+        .may_load(deps.storage, (auction_id, info.sender.clone()))?
+        .ok_or(BidError::InvalidReveal {})?;
+    if commitment != commitment_hash(&info.sender, amount, &salt) {
+        return Err(BidError::InvalidReveal {});
+    }
+
+    // Revealed bid includes the commission already paid, like an open bid
+    let bidder = bidders().load(deps.storage, (auction_id, info.sender.clone()))?;
+    if amount > bidder.amount {
+        return Err(BidError::RevealOverDeposit {});
+    }
+    if amount < bidder.commission_paid {
+        return Err(BidError::BidUnderCommission {});
+    }
+
+    COMMITMENTS.remove(deps.storage, (auction_id, info.sender.clone()));
+    UNREVEALED_BIDDERS.update(deps.storage, auction_id, |count| -> StdResult<_> {
+        Ok(count.unwrap_or_default() - 1)
+    })?;
+    UNREVEALED_DEPOSITS.update(
+        deps.storage,
+        auction_id,
+        |total| -> Result<Uint128, BidError> {
+            Ok(total.unwrap_or_default() - (bidder.amount - bidder.commission_paid))
+        },
+    )?;
+
+    // On equal bids the first one revealed wins
+    let highest_bid = HIGHEST_BID.load(deps.storage, auction_id)?;
+    if amount > highest_bid.bid {
+        RUNNER_UP_BID.save(deps.storage, auction_id, &highest_bid.bid)?;
+        HIGHEST_BID.save(
+            deps.storage,
+            auction_id,
+            &HighestBid {
+                address: info.sender.clone(),
+                bid: amount,
+            },
+        )?;
+    } else if amount > RUNNER_UP_BID.load(deps.storage, auction_id)? {
+        RUNNER_UP_BID.save(deps.storage, auction_id, &amount)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("Execute reveal bid", "OK")
+        .add_attribute("Address calling", info.sender.to_string()))
+}
+
+pub fn buy_now(
+    deps: DepsMut,
+    env: Env,
+    auction_id: u64,
+    sender: Addr,
+    funds: Funds,
+) -> Result<Response, BidError> {
+    let (config, config_events) =
+        load_for_bid(deps.storage, deps.api, &env.block, auction_id, &sender)?;
+
+    let price = config
+        .dutch_price(&env.block)
+        .ok_or(BidError::WrongAuctionMode {})?;
+
+    let payment = accepted_funds(&config, &funds)?;
+    if payment < price {
+        return Err(BidError::PaymentUnderPrice { price });
+    }
+
+    // Buying is a single winning bid at the current price
+    let commission = config.commission.on_bid(price);
+    if price < commission {
+        return Err(BidError::BidUnderCommission {});
+    }
+    let sequence = next_bid_sequence(deps.storage, auction_id)?;
+    save_bidder(
+        deps.storage,
+        auction_id,
+        &sender,
+        &Bidder {
+            amount: price,
+            nb_bids: 1,
+            commission_paid: commission,
+            proxy_bid: None,
+            sequence,
+        },
+    )?;
+    HIGHEST_BID.save(
+        deps.storage,
+        auction_id,
+        &HighestBid {
+            address: sender.clone(),
+            bid: price,
+        },
+    )?;
+
+    PHASE.save(deps.storage, auction_id, &Phase::Closed {})?;
+    AUCTION_OUTCOME.save(
+        deps.storage,
+        auction_id,
+        &AuctionOutcome::Sold {
+            winner: sender.clone(),
+        },
+    )?;
+
+    let mut response = Response::new()
+        .add_events(config_events)
+        .add_attribute("Execute buy now", "OK")
+        .add_submessages(factory_msgs(&config, auction_id)?);
+
+    if !commission.is_zero() {
+        response = response.add_messages(commission_msgs(&config, commission)?);
+    }
+
+    if payment > price {
+        let msg_refund_overpayment = send_msg(&config, &sender, payment - price)?;
+        response = response
+            .add_attribute("Refund", (payment - price).to_string())
+            .add_message(msg_refund_overpayment);
+    }
+
+    settle_sale(deps.storage, auction_id, &config, &sender, price, response)
+}
+
+const MAX_BATCH_BIDS: u64 = 50;
+
+pub fn batch_bid(
+    deps: DepsMut,
+    env: Env,
+    auction_id: u64,
+    sender: Addr,
+    funds: Funds,
+    quantity: Uint128,
+    max_price: Uint128,
+) -> Result<Response, BidError> {
+    let (config, config_events) =
+        load_for_bid(deps.storage, deps.api, &env.block, auction_id, &sender)?;
+    if config.mode != (AuctionMode::Uniform {}) {
+        return Err(BidError::WrongAuctionMode {});
+    }
+
+    let supply = config.lot.as_ref().and_then(Lot::units).unwrap_or_default();
+    if quantity.is_zero() || quantity > supply {
+        return Err(BidError::InvalidBatchBid {});
+    }
+    if max_price.is_zero() || max_price < config.reserve_price.unwrap_or_default() {
+        return Err(BidError::BidTooLow {});
+    }
+
+    // The whole bid is escrowed, commission included
+    let escrow = quantity.checked_mul(max_price).map_err(StdError::from)?;
+    let payment = accepted_funds(&config, &funds)?;
+    if payment < escrow {
+        return Err(BidError::PaymentUnderPrice { price: escrow });
+    }
+    let commission = config.commission.on_bid(escrow);
+    if escrow < commission {
+        return Err(BidError::BidUnderCommission {});
+    }
+
+    // Every batch bid is sorted at close, it has to fit in one transaction
+    let bid_id = NEXT_BATCH_BID_ID.load(deps.storage, auction_id)?;
+    if bid_id >= MAX_BATCH_BIDS {
+        return Err(BidError::TooManyBatchBids {
+            max: MAX_BATCH_BIDS,
+        });
+    }
+    NEXT_BATCH_BID_ID.save(deps.storage, auction_id, &(bid_id + 1))?;
+    BATCH_BIDS.save(
+        deps.storage,
+        (auction_id, bid_id),
+        &BatchBid {
+            bidder: sender.clone(),
+            quantity,
+            max_price,
+        },
+    )?;
+
+    let mut bidder = bidders()
+        .may_load(deps.storage, (auction_id, sender.clone()))?
+        .unwrap_or_default();
+    bidder.amount += escrow;
+    bidder.nb_bids += 1;
+    bidder.commission_paid += commission;
+    bidder.sequence = next_bid_sequence(deps.storage, auction_id)?;
+    save_bidder(deps.storage, auction_id, &sender, &bidder)?;
+
+    let mut response = Response::new()
+        .add_events(config_events)
+        .add_attribute("Execute batch bid", "OK")
+        .add_attribute("Bid id", bid_id.to_string());
+
+    if !commission.is_zero() {
+        response = response.add_messages(commission_msgs(&config, commission)?);
+    }
+
+    if payment > escrow {
+        let msg_refund_overpayment = send_msg(&config, &sender, payment - escrow)?;
+        response = response
+            .add_attribute("Refund", (payment - escrow).to_string())
+            .add_message(msg_refund_overpayment);
+    }
+
+    Ok(response)
+}
+
+pub fn offer(
+    deps: DepsMut,
+    env: Env,
+    auction_id: u64,
+    sender: Addr,
+    funds: Funds,
+    price: Uint128,
+) -> Result<Response, BidError> {
+    let (config, config_events) =
+        load_for_bid(deps.storage, deps.api, &env.block, auction_id, &sender)?;
+    let bond = match config.mode {
+        AuctionMode::Reverse { bond } => bond,
+        _ => return Err(BidError::WrongAuctionMode {}),
+    };
+
+    // Only the first offer comes with funds, later ones just lower the price
+    let paid = match &funds {
+        Funds::Native(coins) if coins.is_empty() => Uint128::zero(),
+        _ => accepted_funds(&config, &funds)?,
+    };
+    let mut bidder = bidders()
+        .may_load(deps.storage, (auction_id, sender.clone()))?
+        .unwrap_or_default();
+    if bidder.amount + paid != bond {
+        return Err(BidError::WrongBond { bond });
+    }
+
+    // First offer has to fit the budget, next ones have to undercut the best offer
+    let best_offer = HIGHEST_BID.load(deps.storage, auction_id)?;
+    let maximum = if best_offer.bid.is_zero() {
+        config.lot.as_ref().and_then(Lot::units).unwrap_or_default()
+    } else {
+        config.maximum_next_offer(best_offer.bid)
+    };
+    if price.is_zero() {
+        return Err(BidError::BidTooLow {});
+    }
+    if price > maximum {
+        return Err(BidError::OfferTooHigh { maximum });
+    }
+
+    let mut response = Response::new()
+        .add_events(config_events)
+        .add_attribute("Execute offer", "OK");
+
+    // A new best offer close to the deadline pushes it back
+    if let Some(new_end) = extend_deadline(deps.storage, auction_id, &config, &env.block)? {
+        response = response
+            .add_attribute("Bidding extended", "OK")
+            .add_attribute("New end", new_end.to_string());
+    }
+
+    if !best_offer.bid.is_zero() && best_offer.address != sender {
+        RUNNER_UP_BID.save(deps.storage, auction_id, &best_offer.bid)?;
+    }
+    HIGHEST_BID.save(
+        deps.storage,
+        auction_id,
+        &HighestBid {
+            address: sender.clone(),
+            bid: price,
+        },
+    )?;
+
+    bidder.amount += paid;
+    bidder.nb_bids += 1;
+    bidder.sequence = next_bid_sequence(deps.storage, auction_id)?;
+    save_bidder(deps.storage, auction_id, &sender, &bidder)?;
+
+    Ok(response)
+}
+
+pub fn proxy_bid(
+    deps: DepsMut,
+    env: Env,
+    auction_id: u64,
+    sender: Addr,
+    funds: Funds,
+    max: Uint128,
+) -> Result<Response, BidError> {
+    let (config, config_events) =
+        load_for_bid(deps.storage, deps.api, &env.block, auction_id, &sender)?;
+    if config.mode != (AuctionMode::Open {})
+        || config.quantity > 1
+        || config.buy_now_price.is_some()
+    {
+        return Err(BidError::WrongAuctionMode {});
+    }
+
+    // The escrow is topped up to the maximum, anything sent over it is refunded
+    let payment = accepted_funds(&config, &funds)?;
+    let mut bidder = bidders()
+        .may_load(deps.storage, (auction_id, sender.clone()))?
+        .unwrap_or_default();
+    if max <= bidder.amount {
+        return Err(BidError::BidTooLow {});
+    }
+    let top_up = max - bidder.amount;
+    if payment < top_up {
+        return Err(BidError::PaymentUnderPrice { price: top_up });
+    }
+
+    let commission = config.commission.on_bid(top_up);
+    if top_up < commission {
+        return Err(BidError::BidUnderCommission {});
+    }
+
+    let highest_bid = HIGHEST_BID.load(deps.storage, auction_id)?;
+    let is_leading = highest_bid.address == sender && !highest_bid.bid.is_zero();
+
+    // Leading bidder only raises his maximum, his visible bid stays
+    if is_leading {
+        bidder.proxy_bid = Some(highest_bid.bid);
+    } else {
+        let minimum_next_bid = config.minimum_next_bid(highest_bid.bid);
+        if max <= highest_bid.bid {
+            return Err(BidError::BidTooLow {});
+        } else if max < minimum_next_bid {
+            return Err(BidError::BidUnderMinimumIncrement {
+                minimum: minimum_next_bid,
+            });
+        }
+        bidder.proxy_bid = Some(max);
+    }
+
+    bidder.amount = max;
+    bidder.nb_bids += 1;
+    bidder.commission_paid += commission;
+    bidder.sequence = next_bid_sequence(deps.storage, auction_id)?;
+    save_bidder(deps.storage, auction_id, &sender, &bidder)?;
+
+    let mut response = Response::new()
+        .add_events(config_events)
+        .add_attribute("Execute proxy bid", "OK");
+
+    if !is_leading {
+        let took_lead = challenge_highest_bid(
+            deps.storage,
+            auction_id,
+            &config,
+            &highest_bid,
+            &sender,
+            max,
+            true,
+        )?;
+        match took_lead {
+            Some(visible) => {
+                bidder.proxy_bid = Some(visible);
+                bidders().save(deps.storage, (auction_id, sender.clone()), &bidder)?;
+            }
+            None => response = response.add_attribute("Outbid by proxy bid", "OK"),
+        }
+
+        if let Some(new_end) = extend_deadline(deps.storage, auction_id, &config, &env.block)? {
+            response = response
+                .add_attribute("Bidding extended", "OK")
+                .add_attribute("New end", new_end.to_string());
+        }
+    }
+
+    if !commission.is_zero() {
+        response = response.add_messages(commission_msgs(&config, commission)?);
+    }
+
+    if payment > top_up {
+        let msg_refund_overpayment = send_msg(&config, &sender, payment - top_up)?;
+        response = response
+            .add_attribute("Refund", (payment - top_up).to_string())
+            .add_message(msg_refund_overpayment);
+    }
+
+    Ok(response)
+}
+
+pub fn receive_nft(
+    deps: DepsMut,
+    info: MessageInfo,
+    msg: Cw721ReceiveMsg,
+) -> Result<Response, BidError> {
+    let auction_id = match from_binary(&msg.msg)? {
+        BidReceiveMsg::DepositLot { auction_id } => auction_id,
+        _ => return Err(BidError::InvalidLot {}),
+    };
+    let config = CONFIG.load(deps.storage, auction_id)?;
+
+    // Only the expected token
+    match &config.lot {
+        Some(Lot::Nft { contract, token_id })
+            if contract == &info.sender && token_id == &msg.token_id => {}
+        _ => return Err(BidError::InvalidLot {}),
+    }
+    record_lot_deposit(deps.storage, auction_id, &config, &msg.sender)?;
+
+    Ok(Response::new()
+        .add_attribute("Execute receive nft", "OK")
+        .add_attribute("Token id", msg.token_id))
+}
+
+pub fn deposit_lot(
+    deps: DepsMut,
+    info: MessageInfo,
+    auction_id: u64,
+) -> Result<Response, BidError> {
+    let config = CONFIG.load(deps.storage, auction_id)?;
+
+    // Exactly the expected coin
+    match &config.lot {
+        Some(Lot::Native { coin }) if info.funds == vec![coin.clone()] => {}
+        _ => return Err(BidError::InvalidLot {}),
+    }
+    record_lot_deposit(deps.storage, auction_id, &config, info.sender.as_str())?;
+
+    Ok(Response::new().add_attribute("Execute deposit lot", "OK"))
+}
+
+fn deposit_cw20_lot(
+    deps: DepsMut,
+    auction_id: u64,
+    depositor: Addr,
+    funds: Funds,
+) -> Result<Response, BidError> {
+    let config = CONFIG.load(deps.storage, auction_id)?;
+
+    // Exactly the expected amount of the expected token
+    match (&config.lot, funds) {
+        (
+            Some(Lot::Cw20 { contract, amount }),
+            Funds::Cw20 {
+                token,
+                amount: sent,
+            },
+        ) if contract == &token && *amount == sent => {}
+        _ => return Err(BidError::InvalidLot {}),
+    }
+    record_lot_deposit(deps.storage, auction_id, &config, depositor.as_str())?;
+
+    Ok(Response::new().add_attribute("Execute deposit lot", "OK"))
+}
@@ -0,0 +1,720 @@
+use crate::error::BidError;
+use crate::factory::msg::FactoryExecuteMsg;
+use crate::msg::{ConfigUpdate, FeeRecipientMsg, LotMsg};
+use crate::state::{
+    bidders, AntiSniping, AuctionMode, AuctionOutcome, Bidder, Clearing, Commission, Config,
+    FeeRecipient, Lot, Phase, Role, AUCTION_OUTCOME, BATCH_BIDS, BIDDER_COUNT, COMMITMENTS, CONFIG,
+    EXTENSIONS, HIGHEST_BID, LOT_DEPOSITED, NEXT_BID_SEQUENCE, OWNERSHIP, PAUSED,
+    PENDING_CONFIG_CHANGES, PENDING_REFUNDS, PHASE, ROLES, UNREVEALED_BIDDERS,
+};
+use cosmwasm_std::{
+    coin, to_binary, to_vec, Addr, Api, BankMsg, BlockInfo, Coin, CosmosMsg, Decimal, Event, Order,
+    StdResult, Storage, SubMsg, Uint128, WasmMsg,
+};
+use cw20::{Cw20ExecuteMsg, Denom};
+use cw_utils::{Duration, Expiration, Scheduled};
+use serde::Serialize;
+use std::cmp::{Ordering, Reverse};
+
+// Tokens sent along a bid : native funds, or an amount transferred by a cw20 contract
+pub enum Funds {
+    Native(Vec<Coin>),
+    Cw20 { token: Addr, amount: Uint128 },
+}
+
+pub fn validate_fee_recipients(
+    api: &dyn Api,
+    recipients: Vec<FeeRecipientMsg>,
+) -> Result<Vec<FeeRecipient>, BidError> {
+    if recipients.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut total_share = Decimal::zero();
+    let mut fee_recipients = Vec::with_capacity(recipients.len());
+    for recipient in recipients {
+        if recipient.share.is_zero() {
+            return Err(BidError::InvalidFeeRecipients {});
+        }
+        total_share += recipient.share;
+        fee_recipients.push(FeeRecipient {
+            address: api.addr_validate(&recipient.address)?,
+            share: recipient.share,
+        });
+    }
+
+    if total_share != Decimal::one() {
+        return Err(BidError::InvalidFeeRecipients {});
+    }
+
+    Ok(fee_recipients)
+}
+
+pub fn validate_commission(commission: &Commission) -> Result<(), BidError> {
+    if let Commission::PercentOfWinningBid { rate } | Commission::PercentOfEachBid { rate } =
+        commission
+    {
+        if *rate > Decimal::one() {
+            return Err(BidError::InvalidCommission {});
+        }
+    }
+    Ok(())
+}
+
+// Bidding window of an auction that is not over yet
+pub fn validate_window(
+    block: &BlockInfo,
+    start: Option<Scheduled>,
+    end: Expiration,
+    anti_sniping: &Option<AntiSniping>,
+) -> Result<(), BidError> {
+    // An auction that is already over, or that would end before it starts, is refused
+    if end.is_expired(block) {
+        return Err(BidError::InvalidBiddingWindow {});
+    }
+    if let Some(start) = start {
+        let start_after_end = match (start, end) {
+            (Scheduled::AtHeight(start), Expiration::AtHeight(end)) => start >= end,
+            (Scheduled::AtTime(start), Expiration::AtTime(end)) => start >= end,
+            _ => false,
+        };
+        if start_after_end {
+            return Err(BidError::InvalidBiddingWindow {});
+        }
+    }
+
+    // Extensions only make sense on a deadline expressed in the same unit
+    if let Some(anti_sniping) = anti_sniping {
+        let same_unit = matches!(
+            (end, anti_sniping.window, anti_sniping.extension),
+            (
+                Expiration::AtHeight(_),
+                Duration::Height(_),
+                Duration::Height(_)
+            ) | (Expiration::AtTime(_), Duration::Time(_), Duration::Time(_))
+        );
+        if !same_unit {
+            return Err(BidError::InvalidAntiSniping {});
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_lot(api: &dyn Api, lot: LotMsg) -> Result<Lot, BidError> {
+    match lot {
+        LotMsg::Nft { contract, token_id } => Ok(Lot::Nft {
+            contract: api.addr_validate(&contract)?,
+            token_id,
+        }),
+        LotMsg::Native { coin } if !coin.amount.is_zero() => Ok(Lot::Native { coin }),
+        LotMsg::Cw20 { contract, amount } if !amount.is_zero() => Ok(Lot::Cw20 {
+            contract: api.addr_validate(&contract)?,
+            amount,
+        }),
+        _ => Err(BidError::InvalidLot {}),
+    }
+}
+
+// Owner rights, lost once the ownership is renounced
+pub fn ensure_owner(
+    storage: &dyn Storage,
+    auction_id: u64,
+    config: &Config,
+    sender: &Addr,
+) -> Result<(), BidError> {
+    let renounced = OWNERSHIP
+        .may_load(storage, auction_id)?
+        .map_or(false, |ownership| ownership.renounced);
+    if *sender != config.owner || renounced {
+        return Err(BidError::Unauthorized {});
+    }
+    Ok(())
+}
+
+// Owner, or an address granted `role`
+pub fn ensure_role(
+    storage: &dyn Storage,
+    auction_id: u64,
+    config: &Config,
+    sender: &Addr,
+    role: Role,
+) -> Result<(), BidError> {
+    if ensure_owner(storage, auction_id, config, sender).is_ok()
+        || ROLES.has(storage, (auction_id, role.key(), sender.clone()))
+    {
+        return Ok(());
+    }
+    Err(BidError::Unauthorized {})
+}
+
+// Only new bids are suspended, reveals and withdrawals keep working
+pub fn ensure_not_paused(storage: &dyn Storage, auction_id: u64) -> Result<(), BidError> {
+    if PAUSED.has(storage, auction_id) {
+        return Err(BidError::AuctionPaused {});
+    }
+    Ok(())
+}
+
+// The owner and role holders steer the auction, they can not take part in it
+pub fn ensure_can_bid(
+    storage: &dyn Storage,
+    auction_id: u64,
+    config: &Config,
+    sender: &Addr,
+) -> Result<(), BidError> {
+    let holds_role = [Role::Admin {}, Role::Operator {}, Role::FeeManager {}]
+        .iter()
+        .any(|role| ROLES.has(storage, (auction_id, role.key(), sender.clone())));
+    if *sender == config.owner || holds_role {
+        return Err(BidError::OwnerCantBid {});
+    }
+    Ok(())
+}
+
+// Longest pause in seconds, past it anyone can resume or close
+pub const MAX_PAUSE: u64 = 7 * 24 * 3600;
+
+pub fn pause_lapsed(paused: &BlockInfo, block: &BlockInfo) -> bool {
+    block.time >= paused.time.plus_seconds(MAX_PAUSE)
+}
+
+// Config of an auction open to a bid from `sender`, matured config changes applied. Every bidding
+// entry point but the sealed commitments goes through it
+pub fn load_for_bid(
+    storage: &mut dyn Storage,
+    api: &dyn Api,
+    block: &BlockInfo,
+    auction_id: u64,
+    sender: &Addr,
+) -> Result<(Config, Vec<Event>), BidError> {
+    let mut config = CONFIG.load(storage, auction_id)?;
+    ensure_not_paused(storage, auction_id)?;
+    let config_events = apply_pending_changes(storage, api, block, auction_id, &mut config)?;
+    match update_phase(storage, auction_id, &config, block)? {
+        Phase::Bidding {} => {}
+        Phase::NotStarted {} => return Err(BidError::BiddingNotStarted {}),
+        Phase::Commit {} => return Err(BidError::WrongAuctionMode {}),
+        Phase::Reveal {} | Phase::Ended {} => return Err(BidError::BiddingExpired {}),
+        Phase::Closed {} => return Err(BidError::BiddingClosed {}),
+    }
+    // Can not bid if owner or role holder
+    ensure_can_bid(storage, auction_id, &config, sender)?;
+    ensure_lot_deposited(storage, auction_id, &config)?;
+    Ok((config, config_events))
+}
+
+// Moves a deadline by the time elapsed since `paused`
+pub fn push_back(deadline: Expiration, paused: &BlockInfo, block: &BlockInfo) -> Expiration {
+    match deadline {
+        Expiration::AtHeight(height) => {
+            Expiration::AtHeight(height + block.height.saturating_sub(paused.height))
+        }
+        Expiration::AtTime(time) => Expiration::AtTime(
+            time.plus_nanos(block.time.nanos().saturating_sub(paused.time.nanos())),
+        ),
+        Expiration::Never {} => Expiration::Never {},
+    }
+}
+
+// Records the lot as escrowed, it has to come once from the owner before the auction is closed
+pub fn record_lot_deposit(
+    storage: &mut dyn Storage,
+    auction_id: u64,
+    config: &Config,
+    depositor: &str,
+) -> Result<(), BidError> {
+    if depositor != config.owner {
+        return Err(BidError::Unauthorized {});
+    }
+    if LOT_DEPOSITED.load(storage, auction_id)? {
+        return Err(BidError::InvalidLot {});
+    }
+    if PHASE.load(storage, auction_id)? == (Phase::Closed {}) {
+        return Err(BidError::BiddingClosed {});
+    }
+    LOT_DEPOSITED.save(storage, auction_id, &true)?;
+    Ok(())
+}
+
+// Bidding waits for the lot to be in escrow
+pub fn ensure_lot_deposited(
+    storage: &dyn Storage,
+    auction_id: u64,
+    config: &Config,
+) -> Result<(), BidError> {
+    if config.lot.is_some() && !LOT_DEPOSITED.load(storage, auction_id)? {
+        return Err(BidError::LotNotDeposited {});
+    }
+    Ok(())
+}
+
+// Releases the escrowed lot to `recipient`, nothing if it was never deposited
+pub fn lot_msgs(
+    storage: &mut dyn Storage,
+    auction_id: u64,
+    config: &Config,
+    recipient: &Addr,
+) -> StdResult<Vec<CosmosMsg>> {
+    match &config.lot {
+        Some(lot) if LOT_DEPOSITED.load(storage, auction_id)? => {
+            LOT_DEPOSITED.save(storage, auction_id, &false)?;
+            Ok(vec![lot.transfer_msg(recipient)?])
+        }
+        _ => Ok(vec![]),
+    }
+}
+
+// Bank send or cw20 transfer of the accepted token
+pub fn send_msg(config: &Config, recipient: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    match &config.accepted_token {
+        Denom::Native(denom) => Ok(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![coin(amount.u128(), denom.clone())],
+        }
+        .into()),
+        Denom::Cw20(token) => Ok(WasmMsg::Execute {
+            contract_addr: token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }
+        .into()),
+    }
+}
+
+// One message per fee recipient, nothing for empty shares
+pub fn commission_msgs(config: &Config, commission: Uint128) -> StdResult<Vec<CosmosMsg>> {
+    config
+        .split_commission(commission)
+        .into_iter()
+        .filter(|(_, amount)| !amount.is_zero())
+        .map(|(address, amount)| send_msg(config, &address, amount))
+        .collect()
+}
+
+pub const FACTORY_NOTIFICATION_REPLY_ID: u64 = 1;
+// Enough to record the close, a factory using more can not hold the auction back
+const FACTORY_NOTIFICATION_GAS_LIMIT: u64 = 200_000;
+
+// Tells the factory which created the auction that it is closed, a failure is caught in the reply
+// so that closing never depends on the factory
+pub fn factory_msgs(config: &Config, auction_id: u64) -> StdResult<Vec<SubMsg>> {
+    match &config.factory {
+        Some(factory) => Ok(vec![SubMsg::reply_on_error(
+            WasmMsg::Execute {
+                contract_addr: factory.to_string(),
+                msg: to_binary(&FactoryExecuteMsg::AuctionClosed { auction_id })?,
+                funds: vec![],
+            },
+            FACTORY_NOTIFICATION_REPLY_ID,
+        )
+        .with_gas_limit(FACTORY_NOTIFICATION_GAS_LIMIT)]),
+        None => Ok(vec![]),
+    }
+}
+
+// Amount of accepted token sent with the message
+pub fn accepted_funds(config: &Config, funds: &Funds) -> Result<Uint128, BidError> {
+    match (&config.accepted_token, funds) {
+        (Denom::Native(accepted_denom), Funds::Native(funds)) => {
+            // If fund do not have valid denom, error
+            if !funds.iter().any(|coin| &coin.denom == accepted_denom) {
+                return Err(BidError::WrongToken {});
+            }
+
+            Ok(funds
+                .iter()
+                .find(|c| &c.denom == accepted_denom)
+                .map(|m| m.amount)
+                .unwrap_or_else(|| Uint128::from(0u128)))
+        }
+        (Denom::Cw20(accepted_token), Funds::Cw20 { token, amount }) if accepted_token == token => {
+            Ok(*amount)
+        }
+        _ => Err(BidError::WrongToken {}),
+    }
+}
+
+// Arrival order of the bids, taken whenever a bidder raises his amount
+pub fn next_bid_sequence(storage: &mut dyn Storage, auction_id: u64) -> StdResult<u64> {
+    let sequence = NEXT_BID_SEQUENCE
+        .may_load(storage, auction_id)?
+        .unwrap_or_default();
+    NEXT_BID_SEQUENCE.save(storage, auction_id, &(sequence + 1))?;
+    Ok(sequence)
+}
+
+// Bidders of the auction from the highest total bid, at most `limit` of them, the earliest first
+// among equal bids
+pub fn top_bidders(
+    storage: &dyn Storage,
+    auction_id: u64,
+    limit: usize,
+) -> StdResult<Vec<(Addr, Bidder)>> {
+    bidders()
+        .idx
+        .amount
+        .sub_prefix(auction_id)
+        .range(storage, None, None, Order::Descending)
+        .take(limit)
+        .map(|item| item.map(|((_, address), bidder)| (address, bidder)))
+        .collect()
+}
+
+// Total `bidder` has to beat : the highest bid, or in multi-winner mode the lowest winning one
+pub fn bid_to_beat(
+    storage: &dyn Storage,
+    auction_id: u64,
+    config: &Config,
+    bidder: Option<&Addr>,
+) -> StdResult<Uint128> {
+    if config.quantity > 1 {
+        return lowest_winning_bid(storage, auction_id, config, bidder);
+    }
+    Ok(HIGHEST_BID.load(storage, auction_id)?.bid)
+}
+
+// Multi-winner mode : total `bidder` has to beat to rank among the winners, zero while an item is
+// left for him
+fn lowest_winning_bid(
+    storage: &dyn Storage,
+    auction_id: u64,
+    config: &Config,
+    bidder: Option<&Addr>,
+) -> StdResult<Uint128> {
+    let quantity = config.quantity as usize;
+    let others: Vec<(Addr, Bidder)> = top_bidders(storage, auction_id, quantity + 1)?
+        .into_iter()
+        .filter(|(address, _)| Some(address) != bidder)
+        .take(quantity)
+        .collect();
+    if others.len() < quantity {
+        return Ok(Uint128::zero());
+    }
+    Ok(others[quantity - 1].1.amount)
+}
+
+// Uniform mode : fills bids from the highest price until the supply runs out, the last bid filled
+// sets the price
+pub fn compute_clearing(
+    storage: &dyn Storage,
+    auction_id: u64,
+    supply: Uint128,
+) -> StdResult<Clearing> {
+    let mut bids = BATCH_BIDS
+        .prefix(auction_id)
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    // Stable sort, at the same price the earlier bid is filled first
+    bids.sort_by_key(|(_, bid)| Reverse(bid.max_price));
+
+    let mut clearing = Clearing {
+        price: Uint128::zero(),
+        sold: Uint128::zero(),
+        supply,
+        fills: vec![],
+    };
+    for (_, bid) in bids {
+        if clearing.sold == supply {
+            break;
+        }
+        let units = bid.quantity.min(supply - clearing.sold);
+        clearing.sold += units;
+        clearing.price = bid.max_price;
+        match clearing
+            .fills
+            .iter_mut()
+            .find(|(bidder, _)| *bidder == bid.bidder)
+        {
+            Some((_, filled)) => *filled += units,
+            None => clearing.fills.push((bid.bidder, units)),
+        }
+    }
+    Ok(clearing)
+}
+
+// Whether the batch refund pays `address` back, not the winners which retract themselves nor the
+// slashed deposits
+pub fn is_refundable(
+    storage: &dyn Storage,
+    auction_id: u64,
+    config: &Config,
+    outcome: &AuctionOutcome,
+    address: &Addr,
+) -> bool {
+    if outcome.is_winner(address) {
+        return false;
+    }
+    if let AuctionMode::Sealed {
+        slash_unrevealed: true,
+        ..
+    } = config.mode
+    {
+        return !COMMITMENTS.has(storage, (auction_id, address.clone()));
+    }
+    true
+}
+
+// Losers the batch refund still has to go through. Until the first refund it is every bidder left
+// at close but the winners and the slashed deposits, the counts do not change once closed
+pub fn pending_refunds(storage: &dyn Storage, auction_id: u64) -> StdResult<u32> {
+    if let Some(pending) = PENDING_REFUNDS.may_load(storage, auction_id)? {
+        return Ok(pending);
+    }
+    let config = CONFIG.load(storage, auction_id)?;
+    let winners = match AUCTION_OUTCOME.load(storage, auction_id)? {
+        AuctionOutcome::Sold { .. } => 1,
+        AuctionOutcome::SoldToMany { winners } => winners.len() as u32,
+        AuctionOutcome::NoSale {} => 0,
+    };
+    let slashed = match config.mode {
+        AuctionMode::Sealed {
+            slash_unrevealed: true,
+            ..
+        } => UNREVEALED_BIDDERS
+            .may_load(storage, auction_id)?
+            .unwrap_or_default(),
+        _ => 0,
+    };
+    let bidders = BIDDER_COUNT
+        .may_load(storage, auction_id)?
+        .unwrap_or_default();
+    Ok(bidders.saturating_sub(winners + slashed))
+}
+
+// `refunded` losers were paid back, by the batch refund or on their own
+pub fn count_refunds(storage: &mut dyn Storage, auction_id: u64, refunded: u32) -> StdResult<()> {
+    let pending = pending_refunds(storage, auction_id)?;
+    PENDING_REFUNDS.save(storage, auction_id, &pending.saturating_sub(refunded))
+}
+
+// Saves `bidder`, counting the new ones
+pub fn save_bidder(
+    storage: &mut dyn Storage,
+    auction_id: u64,
+    address: &Addr,
+    bidder: &Bidder,
+) -> StdResult<()> {
+    if !bidders().has(storage, (auction_id, address.clone())) {
+        let count = BIDDER_COUNT
+            .may_load(storage, auction_id)?
+            .unwrap_or_default();
+        BIDDER_COUNT.save(storage, auction_id, &(count + 1))?;
+    }
+    bidders().save(storage, (auction_id, address.clone()), bidder)
+}
+
+// Whether someone is bidding, withdrawn bids do not count
+pub fn has_bids(storage: &dyn Storage, auction_id: u64) -> bool {
+    bidders()
+        .prefix(auction_id)
+        .keys_raw(storage, None, None, Order::Ascending)
+        .next()
+        .is_some()
+}
+
+// Applies the settings of `update` which are set, returns an event with the old and new value of
+// each of them
+pub fn apply_config_update(
+    storage: &dyn Storage,
+    api: &dyn Api,
+    block: &BlockInfo,
+    auction_id: u64,
+    config: &mut Config,
+    update: ConfigUpdate,
+) -> Result<Vec<Event>, BidError> {
+    let bidding_started = has_bids(storage, auction_id);
+    let locked = |setting: &str| BidError::ConfigLocked {
+        setting: setting.to_string(),
+    };
+    let mut events = vec![];
+
+    if let Some(commission) = update.commission {
+        validate_commission(&commission)?;
+        if bidding_started && !commission.is_at_most(&config.commission) {
+            return Err(locked("commission"));
+        }
+        events.push(config_event("commission", &config.commission, &commission)?);
+        config.commission = commission;
+    }
+
+    if let Some(recipients) = update.fee_recipients {
+        let fee_recipients = validate_fee_recipients(api, recipients)?;
+        events.push(config_event(
+            "fee_recipients",
+            &config.fee_recipients,
+            &fee_recipients,
+        )?);
+        config.fee_recipients = fee_recipients;
+    }
+
+    if let Some(penalty) = update.withdrawal_penalty {
+        if penalty > Decimal::one() {
+            return Err(BidError::InvalidCommission {});
+        }
+        if bidding_started && penalty > config.withdrawal_penalty.unwrap_or_default() {
+            return Err(locked("withdrawal_penalty"));
+        }
+        events.push(config_event(
+            "withdrawal_penalty",
+            &config.withdrawal_penalty,
+            &penalty,
+        )?);
+        config.withdrawal_penalty = Some(penalty);
+    }
+
+    if let Some(accepted_token) = update.accepted_token {
+        if let Denom::Cw20(token) = &accepted_token {
+            api.addr_validate(token.as_str())?;
+        }
+        // The budget is paid in the accepted token
+        if let AuctionMode::Reverse { .. } = config.mode {
+            return Err(BidError::InvalidAuctionMode {});
+        }
+        if bidding_started && accepted_token != config.accepted_token {
+            return Err(locked("accepted_token"));
+        }
+        events.push(config_event(
+            "accepted_token",
+            &config.accepted_token,
+            &accepted_token,
+        )?);
+        config.accepted_token = accepted_token;
+    }
+
+    if let Some(end) = update.end {
+        // Reveals and price decline are scheduled from the deadline
+        if let AuctionMode::Sealed { .. } | AuctionMode::Dutch { .. } = config.mode {
+            return Err(BidError::InvalidAuctionMode {});
+        }
+        validate_window(block, config.start, end, &config.anti_sniping)?;
+        // A queued update can not remove the deadline of a renounced auction
+        let renounced = OWNERSHIP
+            .may_load(storage, auction_id)?
+            .map_or(false, |ownership| ownership.renounced);
+        if renounced && end == (Expiration::Never {}) {
+            return Err(BidError::RenounceWithoutDeadline {});
+        }
+        if bidding_started
+            && !matches!(
+                end.partial_cmp(&config.end),
+                Some(Ordering::Greater | Ordering::Equal)
+            )
+        {
+            return Err(locked("end"));
+        }
+        events.push(config_event("end", &config.end, &end)?);
+        config.end = end;
+    }
+
+    if let Some(reserve_price) = update.reserve_price {
+        if let AuctionMode::Reverse { .. } = config.mode {
+            return Err(BidError::InvalidAuctionMode {});
+        }
+        if config
+            .buy_now_price
+            .as_ref()
+            .map_or(false, |buy_now_price| buy_now_price.price < reserve_price)
+        {
+            return Err(BidError::InvalidBuyNowPrice {});
+        }
+        if bidding_started && reserve_price > config.reserve_price.unwrap_or_default() {
+            return Err(locked("reserve_price"));
+        }
+        events.push(config_event(
+            "reserve_price",
+            &config.reserve_price,
+            &reserve_price,
+        )?);
+        config.reserve_price = Some(reserve_price);
+    }
+
+    Ok(events)
+}
+
+// Applies the queued updates which are now effective, one bidders would lose from since it was
+// queued is dropped
+pub fn apply_pending_changes(
+    storage: &mut dyn Storage,
+    api: &dyn Api,
+    block: &BlockInfo,
+    auction_id: u64,
+    config: &mut Config,
+) -> StdResult<Vec<Event>> {
+    let effective = PENDING_CONFIG_CHANGES
+        .prefix(auction_id)
+        .range(storage, None, None, Order::Ascending)
+        .filter(|item| {
+            item.as_ref()
+                .map_or(true, |(_, change)| change.effective.is_expired(block))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    if effective.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut events = vec![];
+    for (id, change) in effective {
+        PENDING_CONFIG_CHANGES.remove(storage, (auction_id, id));
+        let mut updated = config.clone();
+        match apply_config_update(storage, api, block, auction_id, &mut updated, change.update) {
+            Ok(change_events) => {
+                *config = updated;
+                events.extend(change_events);
+            }
+            Err(err) => events.push(
+                Event::new("config_change_dropped")
+                    .add_attribute("change_id", id.to_string())
+                    .add_attribute("reason", err.to_string()),
+            ),
+        }
+    }
+    CONFIG.save(storage, auction_id, config)?;
+    Ok(events)
+}
+
+// Old and new values in json
+fn config_event<T: Serialize, U: Serialize>(setting: &str, old: &T, new: &U) -> StdResult<Event> {
+    Ok(Event::new("update_config")
+        .add_attribute("setting", setting)
+        .add_attribute("old", String::from_utf8_lossy(&to_vec(old)?))
+        .add_attribute("new", String::from_utf8_lossy(&to_vec(new)?)))
+}
+
+// Anti-sniping : pushes the deadline back when a bid lands inside the window, returns the new end
+pub fn extend_deadline(
+    storage: &mut dyn Storage,
+    auction_id: u64,
+    config: &Config,
+    block: &BlockInfo,
+) -> StdResult<Option<Expiration>> {
+    let anti_sniping = match &config.anti_sniping {
+        Some(anti_sniping) => anti_sniping,
+        None => return Ok(None),
+    };
+    let extensions = EXTENSIONS.load(storage, auction_id)?;
+    if extensions >= anti_sniping.max_extensions
+        || anti_sniping.window.after(block) < config.extended_end(extensions)?
+    {
+        return Ok(None);
+    }
+    EXTENSIONS.save(storage, auction_id, &(extensions + 1))?;
+    Ok(Some(config.extended_end(extensions + 1)?))
+}
+
+// Applies the time driven transitions and saves the phase reached
+pub fn update_phase(
+    storage: &mut dyn Storage,
+    auction_id: u64,
+    config: &Config,
+    block: &BlockInfo,
+) -> StdResult<Phase> {
+    let stored = PHASE.load(storage, auction_id)?;
+    let phase = config.phase_at(stored.clone(), EXTENSIONS.load(storage, auction_id)?, block)?;
+    if phase != stored {
+        PHASE.save(storage, auction_id, &phase)?;
+    }
+    Ok(phase)
+}
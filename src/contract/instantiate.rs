@@ -1,14 +1,56 @@
+use crate::contract::helpers::{
+    validate_commission, validate_fee_recipients, validate_lot, validate_window,
+};
 use crate::error::BidError;
 use crate::msg::BidInstantiateMsg;
-use crate::state::{Config, HighestBid, CONFIG, HIGHEST_BID, IS_BIDDING_CLOSED};
-use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, Uint128};
+use crate::state::{
+    AuctionMode, Config, HighestBid, Lot, Phase, PriceDecline, Settlement, CONFIG, EXTENSIONS,
+    HIGHEST_BID, LOT_DEPOSITED, NEXT_AUCTION_ID, NEXT_BATCH_BID_ID, PHASE, RUNNER_UP_BID,
+    UNREVEALED_DEPOSITS,
+};
+use cosmwasm_std::{Decimal, DepsMut, Env, MessageInfo, Response, Uint128};
+use cw20::Denom;
+use cw_utils::{Expiration, Scheduled};
+use std::cmp::Ordering;
 
 pub fn _instantiate(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: BidInstantiateMsg,
 ) -> Result<Response, BidError> {
+    NEXT_AUCTION_ID.save(deps.storage, &0)?;
+
+    // The auction described by the instantiation gets id 0
+    save_new_auction(deps, env, info, msg)?;
+
+    Ok(Response::new().add_attribute("Instantiate", "Instantiate OK"))
+}
+
+pub fn create_auction(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: BidInstantiateMsg,
+) -> Result<Response, BidError> {
+    // Only a factory instantiating the contract gets notified
+    if msg.factory.is_some() {
+        return Err(BidError::InvalidFactory {});
+    }
+    let auction_id = save_new_auction(deps, env, info, msg)?;
+
+    Ok(Response::new()
+        .add_attribute("Execute create auction", "OK")
+        .add_attribute("Auction id", auction_id.to_string()))
+}
+
+// Validates the auction and saves it under the next id
+fn save_new_auction(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: BidInstantiateMsg,
+) -> Result<u64, BidError> {
     // Config init
 
     let mut owner = info.sender.clone();
@@ -17,16 +59,149 @@ pub fn _instantiate(
     };
 
     let commission = msg.commission;
+    validate_commission(&commission)?;
     let accepted_token = msg.accepted_token;
+    if let Denom::Cw20(token) = &accepted_token {
+        deps.api.addr_validate(token.as_str())?;
+    }
 
-    CONFIG.save(
-        deps.storage,
-        &Config {
-            owner,
-            commission,
-            accepted_token,
-        },
-    )?;
+    let fee_recipients = validate_fee_recipients(deps.api, msg.fee_recipients)?;
+
+    validate_window(&env.block, msg.start, msg.end, &msg.anti_sniping)?;
+
+    // Reveals happen between the end of the commit phase and reveal_end, there is no deadline to
+    // extend while bids are hidden
+    if let AuctionMode::Sealed { reveal_end, .. } = msg.mode {
+        if msg.end == (Expiration::Never {})
+            || reveal_end == (Expiration::Never {})
+            || msg.end.partial_cmp(&reveal_end) != Some(Ordering::Less)
+            || msg.anti_sniping.is_some()
+        {
+            return Err(BidError::InvalidAuctionMode {});
+        }
+    }
+
+    // Dutch price declines from a known time and never goes under the floor
+    let mut start = msg.start;
+    if let AuctionMode::Dutch {
+        start_price,
+        floor_price,
+        decline,
+    } = &msg.mode
+    {
+        let valid_decline = match decline {
+            PriceDecline::Linear {} => matches!(msg.end, Expiration::AtTime(_)),
+            PriceDecline::Stepwise { interval, .. } => *interval > 0,
+        };
+        if start_price < floor_price || !valid_decline || msg.anti_sniping.is_some() {
+            return Err(BidError::InvalidAuctionMode {});
+        }
+        match start {
+            None => start = Some(Scheduled::AtTime(env.block.time)),
+            Some(Scheduled::AtHeight(_)) => return Err(BidError::InvalidAuctionMode {}),
+            Some(Scheduled::AtTime(_)) => {}
+        }
+    }
+
+    // Reaching the buy-now price has to make a sale
+    if let Some(buy_now_price) = &msg.buy_now_price {
+        if msg.mode != (AuctionMode::Open {})
+            || buy_now_price.price.is_zero()
+            || buy_now_price.price < msg.reserve_price.unwrap_or_default()
+            || buy_now_price.disable_at > Some(Decimal::one())
+        {
+            return Err(BidError::InvalidBuyNowPrice {});
+        }
+    }
+
+    if msg.withdrawal_penalty > Some(Decimal::one()) {
+        return Err(BidError::InvalidCommission {});
+    }
+
+    // Every winner pays his own bid for one of the items, nothing is escrowed
+    let quantity = msg.quantity.unwrap_or(1);
+    if quantity == 0
+        || (quantity > 1
+            && (msg.mode != (AuctionMode::Open {})
+                || msg.settlement != (Settlement::FirstPrice {})
+                || msg.buy_now_price.is_some()
+                || msg.lot.is_some()))
+    {
+        return Err(BidError::InvalidAuctionMode {});
+    }
+
+    let lot = msg.lot.map(|lot| validate_lot(deps.api, lot)).transpose()?;
+
+    // The budget is the lot, paid in the accepted token
+    if let AuctionMode::Reverse { .. } = msg.mode {
+        let budget_in_accepted_token = match (&lot, &accepted_token) {
+            (Some(Lot::Native { coin }), Denom::Native(denom)) => &coin.denom == denom,
+            (Some(Lot::Cw20 { contract, .. }), Denom::Cw20(token)) => contract == token,
+            _ => false,
+        };
+        if !budget_in_accepted_token
+            || msg.settlement != (Settlement::FirstPrice {})
+            || msg.reserve_price.is_some()
+        {
+            return Err(BidError::InvalidAuctionMode {});
+        }
+    }
+
+    // Units sold are the ones of the lot, there is no highest bid to protect
+    if msg.mode == (AuctionMode::Uniform {})
+        && (lot.as_ref().and_then(Lot::units).is_none()
+            || msg.settlement != (Settlement::FirstPrice {})
+            || msg.anti_sniping.is_some())
+    {
+        return Err(BidError::InvalidAuctionMode {});
+    }
+
+    let factory = msg
+        .factory
+        .map(|factory| deps.api.addr_validate(&factory))
+        .transpose()?;
+    if factory
+        .as_ref()
+        .map_or(false, |factory| *factory != info.sender)
+    {
+        return Err(BidError::InvalidFactory {});
+    }
+
+    // A native lot sent along the instantiation is escrowed right away, any other funds would be
+    // left unaccounted for
+    let lot_deposited = match &lot {
+        _ if info.funds.is_empty() => false,
+        Some(Lot::Native { coin }) if info.funds == vec![coin.clone()] => true,
+        _ => return Err(BidError::InvalidLot {}),
+    };
+
+    let config = Config {
+        owner,
+        commission,
+        accepted_token,
+        start,
+        end: msg.end,
+        anti_sniping: msg.anti_sniping,
+        reserve_price: msg.reserve_price,
+        min_increment: msg.min_increment,
+        fee_recipients,
+        mode: msg.mode,
+        settlement: msg.settlement,
+        buy_now_price: msg.buy_now_price,
+        lot,
+        factory,
+        quantity,
+        withdrawal_penalty: msg.withdrawal_penalty,
+        config_timelock: msg.config_timelock,
+    };
+    let auction_id = NEXT_AUCTION_ID.load(deps.storage)?;
+    NEXT_AUCTION_ID.save(deps.storage, &(auction_id + 1))?;
+
+    CONFIG.save(deps.storage, auction_id, &config)?;
+    EXTENSIONS.save(deps.storage, auction_id, &0)?;
+    UNREVEALED_DEPOSITS.save(deps.storage, auction_id, &Uint128::zero())?;
+    LOT_DEPOSITED.save(deps.storage, auction_id, &lot_deposited)?;
+    NEXT_BATCH_BID_ID.save(deps.storage, auction_id, &0)?;
 
     // Highest Bid init
     let highest_bid = HighestBid {
@@ -34,10 +209,15 @@ pub fn _instantiate(
         bid: Uint128::from(0u128),
     };
 
-    HIGHEST_BID.save(deps.storage, &highest_bid)?;
+    HIGHEST_BID.save(deps.storage, auction_id, &highest_bid)?;
+    RUNNER_UP_BID.save(deps.storage, auction_id, &Uint128::zero())?;
 
-    // Bidding open at start
-    IS_BIDDING_CLOSED.save(deps.storage, &false)?;
+    // Bidding open at start, unless scheduled later
+    PHASE.save(
+        deps.storage,
+        auction_id,
+        &config.phase_at(Phase::NotStarted {}, 0, &env.block)?,
+    )?;
 
-    Ok(Response::new().add_attribute("Instantiate", "Instantiate OK"))
+    Ok(auction_id)
 }
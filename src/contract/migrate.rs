@@ -0,0 +1,132 @@
+use crate::contract::helpers::save_bidder;
+use crate::contract::{CONTRACT_NAME, CONTRACT_VERSION};
+use crate::error::BidError;
+use crate::msg::BidMigrateMsg;
+use crate::state::{
+    AuctionMode, AuctionOutcome, Bidder, Commission, Config, HighestBid, Phase, Settlement,
+    AUCTION_OUTCOME, CONFIG, EXTENSIONS, HIGHEST_BID, LOT_DEPOSITED, NEXT_AUCTION_ID, PHASE,
+    RUNNER_UP_BID, UNREVEALED_DEPOSITS,
+};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    Addr, Coin, DepsMut, Env, Order, Response, StdError, StdResult, Storage, Uint128,
+};
+use cw2::{get_contract_version, set_contract_version};
+use cw20::Denom;
+use cw_storage_plus::{Item, Map};
+use cw_utils::Expiration;
+
+// Storage of the single auction version, moved to auction 0
+#[cw_serde]
+struct LegacyConfig {
+    owner: Addr,
+    commission: Uint128,
+    accepted_token: Coin,
+}
+const LEGACY_CONFIG: Item<LegacyConfig> = Item::new("config");
+// the u32 is how many times the bidder bid, the coin includes the commissions
+const LEGACY_BIDDERS: Map<Addr, (Coin, u32)> = Map::new("bidders");
+const LEGACY_HIGHEST_BID: Item<HighestBid> = Item::new("highets_bid");
+const LEGACY_IS_BIDDING_CLOSED: Item<bool> = Item::new("is_bidding_close");
+const LEGACY_BID_WINNER: Item<Addr> = Item::new("bid_winner");
+
+pub fn _migrate(deps: DepsMut, _env: Env, _msg: BidMigrateMsg) -> Result<Response, BidError> {
+    let version = get_contract_version(deps.storage)?;
+    if version.contract != CONTRACT_NAME {
+        return Err(StdError::generic_err("Can only migrate from a bidding contract").into());
+    }
+
+    let mut response = Response::new().add_attribute("Migrate", "OK");
+
+    if let Some(legacy_config) = LEGACY_CONFIG.may_load(deps.storage)? {
+        migrate_single_auction(deps.storage, legacy_config)?;
+        response = response.add_attribute("Single auction moved to id", "0");
+    }
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(response)
+}
+
+fn migrate_single_auction(storage: &mut dyn Storage, legacy_config: LegacyConfig) -> StdResult<()> {
+    let is_closed = LEGACY_IS_BIDDING_CLOSED.load(storage)?;
+    let highest_bid = LEGACY_HIGHEST_BID.load(storage)?;
+
+    // Open auction paying a flat commission on every bid, closed by the owner
+    let config = Config {
+        owner: legacy_config.owner,
+        commission: Commission::FlatPerBid {
+            amount: legacy_config.commission,
+        },
+        accepted_token: Denom::Native(legacy_config.accepted_token.denom),
+        start: None,
+        end: Expiration::Never {},
+        anti_sniping: None,
+        reserve_price: None,
+        min_increment: None,
+        fee_recipients: vec![],
+        mode: AuctionMode::Open {},
+        settlement: Settlement::FirstPrice {},
+        buy_now_price: None,
+        lot: None,
+        factory: None,
+        quantity: 1,
+        withdrawal_penalty: None,
+        config_timelock: None,
+    };
+
+    let legacy_bidders = LEGACY_BIDDERS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut runner_up_bid = Uint128::zero();
+    for (address, (amount, nb_bids)) in legacy_bidders {
+        let mut bidder = Bidder {
+            amount: amount.amount,
+            nb_bids,
+            commission_paid: legacy_config.commission * Uint128::from(nb_bids),
+            proxy_bid: None,
+            sequence: 0,
+        };
+        if address == highest_bid.address {
+            // The winner of a closed auction has already been paid out
+            if is_closed {
+                bidder.amount = Uint128::zero();
+                bidder.commission_paid = Uint128::zero();
+            }
+        } else {
+            runner_up_bid = runner_up_bid.max(amount.amount);
+        }
+        save_bidder(storage, 0, &address, &bidder)?;
+        LEGACY_BIDDERS.remove(storage, address);
+    }
+
+    if is_closed {
+        // The legacy close recorded the instantiator as winner when nobody bid
+        let outcome = if highest_bid.bid.is_zero() {
+            AuctionOutcome::NoSale {}
+        } else {
+            AuctionOutcome::Sold {
+                winner: LEGACY_BID_WINNER.load(storage)?,
+            }
+        };
+        PHASE.save(storage, 0, &Phase::Closed {})?;
+        AUCTION_OUTCOME.save(storage, 0, &outcome)?;
+    } else {
+        PHASE.save(storage, 0, &Phase::Bidding {})?;
+    }
+
+    CONFIG.save(storage, 0, &config)?;
+    HIGHEST_BID.save(storage, 0, &highest_bid)?;
+    RUNNER_UP_BID.save(storage, 0, &runner_up_bid)?;
+    EXTENSIONS.save(storage, 0, &0)?;
+    UNREVEALED_DEPOSITS.save(storage, 0, &Uint128::zero())?;
+    LOT_DEPOSITED.save(storage, 0, &false)?;
+    NEXT_AUCTION_ID.save(storage, &1)?;
+
+    LEGACY_CONFIG.remove(storage);
+    LEGACY_HIGHEST_BID.remove(storage);
+    LEGACY_IS_BIDDING_CLOSED.remove(storage);
+    LEGACY_BID_WINNER.remove(storage);
+
+    Ok(())
+}
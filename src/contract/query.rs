@@ -1,25 +1,96 @@
-use crate::msg::BidQueryMsg;
-use crate::state::{HighestBid, BIDDERS, BID_WINNER, CONFIG, HIGHEST_BID, IS_BIDDING_CLOSED};
-use cosmwasm_std::{to_binary, Addr, Binary, Deps, Env, StdResult};
+use crate::contract::helpers::{bid_to_beat, compute_clearing, pending_refunds};
+use crate::msg::{
+    AuctionResponse, BidQueryMsg, BiddingWindowResponse, LotResponse, OwnershipResponse,
+    WinningBidderResponse,
+};
+use crate::state::{
+    bidders, AuctionMode, AuctionOutcome, Clearing, FeeRecipient, HighestBid, Lot,
+    PendingConfigChange, Phase, Role, AUCTION_OUTCOME, CONFIG, EXTENSIONS, HIGHEST_BID,
+    LOT_DEPOSITED, OWNERSHIP, PAUSED, PENDING_CONFIG_CHANGES, PHASE, ROLES, RUNNER_UP_BID,
+};
+use cosmwasm_std::{
+    to_binary, Addr, Binary, Decimal, Deps, Env, Order, StdError, StdResult, Uint128,
+};
+use cw20::Denom;
+use cw_storage_plus::Bound;
+use cw_utils::{Duration, Expiration};
 
-pub fn _query(deps: Deps, _env: Env, msg: BidQueryMsg) -> StdResult<Binary> {
+pub fn _query(deps: Deps, env: Env, msg: BidQueryMsg) -> StdResult<Binary> {
     match msg {
-        BidQueryMsg::GetTotalBidAddr { address } => to_binary(&get_total_bid_addr(deps, address)?),
-        BidQueryMsg::GetHighestBid {} => to_binary(&get_highest_bid(deps)?),
-        BidQueryMsg::GetWinningBidder {} => to_binary(&get_winning_bider(deps)?),
-        BidQueryMsg::GetAcceptedDenom {} => to_binary(&get_accepted_denom(deps)?),
-        BidQueryMsg::IsBiddingClosed {} => to_binary(&is_bidding_closed(deps)?),
+        BidQueryMsg::GetTotalBidAddr {
+            auction_id,
+            address,
+        } => to_binary(&get_total_bid_addr(deps, auction_id, address)?),
+        BidQueryMsg::GetHighestBid { auction_id } => to_binary(&get_highest_bid(deps, auction_id)?),
+        BidQueryMsg::GetWinningBidder { auction_id } => {
+            to_binary(&get_winning_bider(deps, auction_id)?)
+        }
+        BidQueryMsg::GetAcceptedDenom { auction_id } => {
+            to_binary(&get_accepted_denom(deps, auction_id)?)
+        }
+        BidQueryMsg::IsBiddingClosed { auction_id } => {
+            to_binary(&is_bidding_closed(deps, auction_id)?)
+        }
+        BidQueryMsg::GetBiddingWindow { auction_id } => {
+            to_binary(&get_bidding_window(deps, env, auction_id)?)
+        }
+        BidQueryMsg::GetMinimumNextBid { auction_id, bidder } => {
+            to_binary(&get_minimum_next_bid(deps, auction_id, bidder)?)
+        }
+        BidQueryMsg::GetFeeRecipients { auction_id } => {
+            to_binary(&get_fee_recipients(deps, auction_id)?)
+        }
+        BidQueryMsg::GetPhase { auction_id } => to_binary(&get_phase(deps, env, auction_id)?),
+        BidQueryMsg::GetRunnerUpBid { auction_id } => {
+            to_binary(&get_runner_up_bid(deps, auction_id)?)
+        }
+        BidQueryMsg::GetCurrentPrice { auction_id } => {
+            to_binary(&get_current_price(deps, env, auction_id)?)
+        }
+        BidQueryMsg::GetBuyNowPrice { auction_id } => {
+            to_binary(&get_buy_now_price(deps, auction_id)?)
+        }
+        BidQueryMsg::GetLot { auction_id } => to_binary(&get_lot(deps, auction_id)?),
+        BidQueryMsg::SimulateClearing { auction_id } => {
+            to_binary(&simulate_clearing(deps, auction_id)?)
+        }
+        BidQueryMsg::GetPendingRefunds { auction_id } => {
+            to_binary(&get_pending_refunds(deps, auction_id)?)
+        }
+        BidQueryMsg::GetPendingConfigChanges { auction_id } => {
+            to_binary(&get_pending_config_changes(deps, auction_id)?)
+        }
+        BidQueryMsg::GetOwnership { auction_id } => to_binary(&get_ownership(deps, auction_id)?),
+        BidQueryMsg::GetRoleMembers { auction_id, role } => {
+            to_binary(&get_role_members(deps, auction_id, role)?)
+        }
+        BidQueryMsg::IsPaused { auction_id } => to_binary(&is_paused(deps, auction_id)?),
+        BidQueryMsg::GetAuction { auction_id } => to_binary(&get_auction(deps, env, auction_id)?),
+        BidQueryMsg::ListAuctions {
+            status,
+            seller,
+            start_after,
+            limit,
+        } => to_binary(&list_auctions(
+            deps,
+            env,
+            status,
+            seller,
+            start_after,
+            limit,
+        )?),
     }
 }
 
-pub fn get_total_bid_addr(deps: Deps, address_to_check: Addr) -> StdResult<u128> {
-    let bidder = BIDDERS.load(deps.storage, address_to_check)?;
-    let total_bid = bidder.0.amount;
+pub fn get_total_bid_addr(deps: Deps, auction_id: u64, address_to_check: Addr) -> StdResult<u128> {
+    let bidder = bidders().load(deps.storage, (auction_id, address_to_check))?;
+    // Maximum of a proxy bidder stays hidden
+    let total_bid = bidder.proxy_bid.unwrap_or(bidder.amount);
     Ok(total_bid.u128())
 }
 
-pub fn get_highest_bid(deps: Deps) -> StdResult<HighestBid> {
-    let highest_bidder = HIGHEST_BID.load(deps.storage).unwrap();
+pub fn get_highest_bid(deps: Deps, auction_id: u64) -> StdResult<HighestBid> {
+    let highest_bidder = HIGHEST_BID.load(deps.storage, auction_id)?;
 
     Ok(HighestBid {
         address: highest_bidder.address,
@@ -27,18 +98,210 @@ pub fn get_highest_bid(deps: Deps) -> StdResult<HighestBid> {
     })
 }
 
-pub fn get_winning_bider(deps: Deps) -> StdResult<Addr> {
-    if IS_BIDDING_CLOSED.load(deps.storage).unwrap() {
-        Ok(BID_WINNER.load(deps.storage)?)
+pub fn get_winning_bider(deps: Deps, auction_id: u64) -> StdResult<WinningBidderResponse> {
+    if is_bidding_closed(deps, auction_id)? {
+        match AUCTION_OUTCOME.load(deps.storage, auction_id)? {
+            AuctionOutcome::Sold { winner } => {
+                Ok(WinningBidderResponse::Winner { address: winner })
+            }
+            AuctionOutcome::SoldToMany { winners } => {
+                Ok(WinningBidderResponse::Winners { addresses: winners })
+            }
+            AuctionOutcome::NoSale {} => Ok(WinningBidderResponse::NoSale {}),
+        }
     } else {
-        Ok(Addr::unchecked("nowinneryet"))
+        Ok(WinningBidderResponse::NoWinnerYet {})
     }
 }
 
-pub fn get_accepted_denom(deps: Deps) -> StdResult<String> {
-    Ok(CONFIG.load(deps.storage)?.accepted_token.denom)
+pub fn get_accepted_denom(deps: Deps, auction_id: u64) -> StdResult<Denom> {
+    Ok(CONFIG.load(deps.storage, auction_id)?.accepted_token)
 }
 
-pub fn is_bidding_closed(deps: Deps) -> StdResult<bool> {
-    Ok(IS_BIDDING_CLOSED.load(deps.storage).unwrap())
+pub fn is_bidding_closed(deps: Deps, auction_id: u64) -> StdResult<bool> {
+    Ok(PHASE.load(deps.storage, auction_id)? == Phase::Closed {})
+}
+
+pub fn get_bidding_window(
+    deps: Deps,
+    env: Env,
+    auction_id: u64,
+) -> StdResult<BiddingWindowResponse> {
+    let config = CONFIG.load(deps.storage, auction_id)?;
+    let extensions = EXTENSIONS.load(deps.storage, auction_id)?;
+    let end = config.extended_end(extensions)?;
+
+    let time_remaining = match end {
+        Expiration::AtHeight(height) => {
+            Some(Duration::Height(height.saturating_sub(env.block.height)))
+        }
+        Expiration::AtTime(time) => Some(Duration::Time(
+            time.seconds().saturating_sub(env.block.time.seconds()),
+        )),
+        Expiration::Never {} => None,
+    };
+
+    Ok(BiddingWindowResponse {
+        start: config.start,
+        end,
+        time_remaining,
+        anti_sniping: config.anti_sniping,
+        extensions,
+    })
+}
+
+pub fn get_minimum_next_bid(
+    deps: Deps,
+    auction_id: u64,
+    bidder: Option<String>,
+) -> StdResult<Uint128> {
+    let config = CONFIG.load(deps.storage, auction_id)?;
+    let bidder = bidder
+        .map(|bidder| deps.api.addr_validate(&bidder))
+        .transpose()?;
+    let bid_to_beat = bid_to_beat(deps.storage, auction_id, &config, bidder.as_ref())?;
+    Ok(config.minimum_next_bid(bid_to_beat))
+}
+
+// Owner alone when no recipient was configured
+pub fn get_fee_recipients(deps: Deps, auction_id: u64) -> StdResult<Vec<FeeRecipient>> {
+    let config = CONFIG.load(deps.storage, auction_id)?;
+    if config.fee_recipients.is_empty() {
+        return Ok(vec![FeeRecipient {
+            address: config.owner,
+            share: Decimal::one(),
+        }]);
+    }
+    Ok(config.fee_recipients)
+}
+
+pub fn get_phase(deps: Deps, env: Env, auction_id: u64) -> StdResult<Phase> {
+    let config = CONFIG.load(deps.storage, auction_id)?;
+    config.phase_at(
+        PHASE.load(deps.storage, auction_id)?,
+        EXTENSIONS.load(deps.storage, auction_id)?,
+        &env.block,
+    )
+}
+
+pub fn get_runner_up_bid(deps: Deps, auction_id: u64) -> StdResult<Uint128> {
+    RUNNER_UP_BID.load(deps.storage, auction_id)
+}
+
+pub fn get_current_price(deps: Deps, env: Env, auction_id: u64) -> StdResult<Uint128> {
+    CONFIG
+        .load(deps.storage, auction_id)?
+        .dutch_price(&env.block)
+        .ok_or_else(|| StdError::generic_err("Not a dutch auction"))
+}
+
+pub fn get_buy_now_price(deps: Deps, auction_id: u64) -> StdResult<Option<Uint128>> {
+    if is_bidding_closed(deps, auction_id)? {
+        return Ok(None);
+    }
+    let config = CONFIG.load(deps.storage, auction_id)?;
+    let highest_bid = HIGHEST_BID.load(deps.storage, auction_id)?;
+    Ok(config.active_buy_now_price(highest_bid.bid))
+}
+
+pub fn get_lot(deps: Deps, auction_id: u64) -> StdResult<LotResponse> {
+    Ok(LotResponse {
+        lot: CONFIG.load(deps.storage, auction_id)?.lot,
+        escrowed: LOT_DEPOSITED.load(deps.storage, auction_id)?,
+    })
+}
+
+pub fn simulate_clearing(deps: Deps, auction_id: u64) -> StdResult<Clearing> {
+    let config = CONFIG.load(deps.storage, auction_id)?;
+    if config.mode != (AuctionMode::Uniform {}) {
+        return Err(StdError::generic_err("Not a uniform price auction"));
+    }
+    let supply = config.lot.as_ref().and_then(Lot::units).unwrap_or_default();
+    compute_clearing(deps.storage, auction_id, supply)
+}
+
+pub fn get_pending_refunds(deps: Deps, auction_id: u64) -> StdResult<u32> {
+    if !is_bidding_closed(deps, auction_id)? {
+        return Err(StdError::generic_err("Bidding is not closed"));
+    }
+    pending_refunds(deps.storage, auction_id)
+}
+
+pub fn get_pending_config_changes(
+    deps: Deps,
+    auction_id: u64,
+) -> StdResult<Vec<PendingConfigChange>> {
+    PENDING_CONFIG_CHANGES
+        .prefix(auction_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, change)| change))
+        .collect()
+}
+
+pub fn get_ownership(deps: Deps, auction_id: u64) -> StdResult<OwnershipResponse> {
+    let config = CONFIG.load(deps.storage, auction_id)?;
+    let ownership = OWNERSHIP
+        .may_load(deps.storage, auction_id)?
+        .unwrap_or_default();
+    Ok(OwnershipResponse {
+        owner: (!ownership.renounced).then_some(config.owner),
+        pending_owner: ownership.pending_owner,
+        pending_expiry: ownership.pending_expiry,
+    })
+}
+
+pub fn get_role_members(deps: Deps, auction_id: u64, role: Role) -> StdResult<Vec<Addr>> {
+    ROLES
+        .prefix((auction_id, role.key()))
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect()
+}
+
+pub fn is_paused(deps: Deps, auction_id: u64) -> StdResult<bool> {
+    Ok(PAUSED.has(deps.storage, auction_id))
+}
+
+pub fn get_auction(deps: Deps, env: Env, auction_id: u64) -> StdResult<AuctionResponse> {
+    Ok(AuctionResponse {
+        auction_id,
+        config: CONFIG.load(deps.storage, auction_id)?,
+        phase: get_phase(deps, env, auction_id)?,
+        highest_bid: HIGHEST_BID.load(deps.storage, auction_id)?,
+    })
+}
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn list_auctions(
+    deps: Deps,
+    env: Env,
+    status: Option<Phase>,
+    seller: Option<String>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<AuctionResponse>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let seller = seller
+        .map(|seller| deps.api.addr_validate(&seller))
+        .transpose()?;
+
+    let mut auctions = vec![];
+    for auction_id in CONFIG.keys(deps.storage, start, None, Order::Ascending) {
+        let auction = get_auction(deps, env.clone(), auction_id?)?;
+        let seller_matches = seller
+            .as_ref()
+            .map_or(true, |seller| &auction.config.owner == seller);
+        let status_matches = status
+            .as_ref()
+            .map_or(true, |status| &auction.phase == status);
+        if seller_matches && status_matches {
+            auctions.push(auction);
+            if auctions.len() == limit {
+                break;
+            }
+        }
+    }
+    Ok(auctions)
 }
@@ -0,0 +1,19 @@
+use crate::contract::helpers::FACTORY_NOTIFICATION_REPLY_ID;
+use crate::error::BidError;
+use cosmwasm_std::{DepsMut, Env, Reply, Response, StdError, SubMsgResult};
+
+pub fn _reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, BidError> {
+    match msg.id {
+        FACTORY_NOTIFICATION_REPLY_ID => factory_notification_failed(msg),
+        id => Err(StdError::generic_err(format!("Unknown reply id {}", id)).into()),
+    }
+}
+
+// The registry is left out of date, the auction is closed anyway
+pub fn factory_notification_failed(msg: Reply) -> Result<Response, BidError> {
+    let error = match msg.result {
+        SubMsgResult::Err(error) => error,
+        SubMsgResult::Ok(_) => String::new(),
+    };
+    Ok(Response::new().add_attribute("Factory notification failed", error))
+}
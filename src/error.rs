@@ -1,4 +1,5 @@
-use cosmwasm_std::{DecimalRangeExceeded, StdError};
+use cosmwasm_std::{DecimalRangeExceeded, StdError, Uint128};
+use cw_utils::ParseReplyError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -9,13 +10,16 @@ pub enum BidError {
     #[error("{0}")]
     Decimal(#[from] DecimalRangeExceeded),
 
+    #[error("{0}")]
+    ParseReply(#[from] ParseReplyError),
+
     #[error("Bidding is closed")]
     BiddingClosed {},
 
     #[error("Token not accepted")]
     WrongToken {},
 
-    #[error("Owner can not bid")]
+    #[error("Owner and role holders can not bid")]
     OwnerCantBid {},
 
     #[error("Bid is under commission")]
@@ -35,4 +39,106 @@ pub enum BidError {
 
     #[error("Winner can't retract")]
     WinnerCantRetract {},
+
+    #[error("Bidding has not started yet")]
+    BiddingNotStarted {},
+
+    #[error("Bidding period is over")]
+    BiddingExpired {},
+
+    #[error("Invalid bidding window")]
+    InvalidBiddingWindow {},
+
+    #[error("Anti-sniping needs a deadline and durations of the same unit")]
+    InvalidAntiSniping {},
+
+    #[error("Commission rate can not exceed 100%")]
+    InvalidCommission {},
+
+    #[error("Fee recipients shares must be positive and sum to 100%")]
+    InvalidFeeRecipients {},
+
+    #[error("Not available in this auction mode")]
+    WrongAuctionMode {},
+
+    #[error("Lot has not been deposited yet")]
+    LotNotDeposited {},
+
+    #[error("Not the lot expected by this auction")]
+    InvalidLot {},
+
+    #[error("Invalid auction mode")]
+    InvalidAuctionMode {},
+
+    #[error("Buy-now price must be positive, over the reserve price and only used in open mode")]
+    InvalidBuyNowPrice {},
+
+    #[error("Bidding is not over yet")]
+    BiddingNotOver {},
+
+    #[error("Not in commit phase")]
+    NotCommitPhase {},
+
+    #[error("Not in reveal phase")]
+    NotRevealPhase {},
+
+    #[error("Revealed bid does not match the commitment")]
+    InvalidReveal {},
+
+    #[error("Revealed bid is over the deposit")]
+    RevealOverDeposit {},
+
+    #[error("Unrevealed deposit has been slashed")]
+    DepositSlashed {},
+
+    #[error("Batch bid quantity must be positive and within the supply")]
+    InvalidBatchBid {},
+
+    #[error("Suppliers post a bond of {bond} with their first offer only")]
+    WrongBond { bond: Uint128 },
+
+    #[error("Offer has to be at most {maximum}")]
+    OfferTooHigh { maximum: Uint128 },
+
+    #[error("Bidding by proxy, the maximum is raised with ProxyBid")]
+    ProxyBidActive {},
+
+    #[error("Winning bid can not be withdrawn")]
+    WinnerCantWithdraw {},
+
+    #[error("No ownership transfer pending")]
+    NoPendingOwnership {},
+
+    #[error("Ownership transfer has expired")]
+    OwnershipTransferExpired {},
+
+    #[error("Ownership of an auction without deadline can not be renounced")]
+    RenounceWithoutDeadline {},
+
+    #[error("Auction is paused")]
+    AuctionPaused {},
+
+    #[error("{setting} can not be changed against the bidders once bidding started")]
+    ConfigLocked { setting: String },
+
+    #[error("Only the factory instantiating the auction can be notified")]
+    InvalidFactory {},
+
+    #[error("Config update has to set at least one setting")]
+    EmptyConfigUpdate {},
+
+    #[error("At most {max} batch bids can be placed")]
+    TooManyBatchBids { max: u64 },
+
+    #[error("At most {max} config changes can be pending")]
+    TooManyPendingConfigChanges { max: u32 },
+
+    #[error("No pending config change with this id")]
+    ConfigChangeNotFound {},
+
+    #[error("Payment is under the current price of {price}")]
+    PaymentUnderPrice { price: Uint128 },
+
+    #[error("Bid is under the minimum increment, total bid must be at least {minimum}")]
+    BidUnderMinimumIncrement { minimum: Uint128 },
 }
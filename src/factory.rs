@@ -0,0 +1,52 @@
+use crate::error::BidError;
+use crate::factory::msg::{FactoryExecuteMsg, FactoryInstantiateMsg, FactoryQueryMsg};
+#[cfg(all(feature = "factory", not(feature = "library")))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult};
+use cw2::set_contract_version;
+
+mod execute;
+mod instantiate;
+pub mod msg;
+mod query;
+mod reply;
+pub mod state;
+use crate::factory::execute::_execute;
+use crate::factory::instantiate::_instantiate;
+use crate::factory::query::_query;
+use crate::factory::reply::_reply;
+
+// Built instead of the bidding contract with the factory feature
+const CONTRACT_NAME: &str = concat!(env!("CARGO_PKG_NAME"), "_factory");
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg_attr(all(feature = "factory", not(feature = "library")), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: FactoryInstantiateMsg,
+) -> Result<Response, BidError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    _instantiate(deps, env, info, msg)
+}
+
+#[cfg_attr(all(feature = "factory", not(feature = "library")), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: FactoryExecuteMsg,
+) -> Result<Response, BidError> {
+    _execute(deps, env, info, msg)
+}
+
+#[cfg_attr(all(feature = "factory", not(feature = "library")), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: FactoryQueryMsg) -> StdResult<Binary> {
+    _query(deps, env, msg)
+}
+
+#[cfg_attr(all(feature = "factory", not(feature = "library")), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, BidError> {
+    _reply(deps, env, msg)
+}
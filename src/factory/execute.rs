@@ -0,0 +1,89 @@
+use crate::error::BidError;
+use crate::factory::msg::FactoryExecuteMsg;
+use crate::factory::state::{AuctionStatus, AUCTIONS, FACTORY_CONFIG, PENDING_AUCTION};
+use crate::msg::BidInstantiateMsg;
+use cosmwasm_std::{to_binary, DepsMut, Env, MessageInfo, Response, SubMsg, WasmMsg};
+
+pub const INSTANTIATE_AUCTION_REPLY_ID: u64 = 1;
+
+pub fn _execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: FactoryExecuteMsg,
+) -> Result<Response, BidError> {
+    match msg {
+        FactoryExecuteMsg::CreateAuction(msg) => create_auction(deps, env, info, *msg),
+        FactoryExecuteMsg::UpdateCodeId { code_id } => update_code_id(deps, info, code_id),
+        FactoryExecuteMsg::AuctionClosed { auction_id } => auction_closed(deps, info, auction_id),
+    }
+}
+
+pub fn create_auction(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    mut msg: BidInstantiateMsg,
+) -> Result<Response, BidError> {
+    let config = FACTORY_CONFIG.load(deps.storage)?;
+
+    let seller = match &msg.owner {
+        Some(owner) => deps.api.addr_validate(owner)?,
+        None => info.sender,
+    };
+    PENDING_AUCTION.save(deps.storage, &(seller.clone(), msg.accepted_token.clone()))?;
+
+    // The factory is the instantiator, seller and notifications have to be explicit
+    msg.owner = Some(seller.to_string());
+    msg.factory = Some(env.contract.address.to_string());
+
+    let instantiate_msg = WasmMsg::Instantiate {
+        admin: Some(config.owner.to_string()),
+        code_id: config.bidding_code_id,
+        msg: to_binary(&msg)?,
+        funds: info.funds,
+        label: format!("Auction by {}", seller),
+    };
+
+    Ok(Response::new()
+        .add_attribute("Execute create auction", "OK")
+        .add_submessage(SubMsg::reply_on_success(
+            instantiate_msg,
+            INSTANTIATE_AUCTION_REPLY_ID,
+        )))
+}
+
+pub fn update_code_id(
+    deps: DepsMut,
+    info: MessageInfo,
+    code_id: u64,
+) -> Result<Response, BidError> {
+    let mut config = FACTORY_CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(BidError::Unauthorized {});
+    }
+
+    // Auctions already created keep their code
+    config.bidding_code_id = code_id;
+    FACTORY_CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attribute("Execute update code id", "OK"))
+}
+
+pub fn auction_closed(
+    deps: DepsMut,
+    info: MessageInfo,
+    auction_id: u64,
+) -> Result<Response, BidError> {
+    let mut auction = AUCTIONS
+        .may_load(deps.storage, info.sender.clone())?
+        .ok_or(BidError::Unauthorized {})?;
+
+    // Auctions later created inside the bidding contract are not in the registry
+    if auction_id == 0 {
+        auction.status = AuctionStatus::Closed {};
+        AUCTIONS.save(deps.storage, info.sender, &auction)?;
+    }
+
+    Ok(Response::new().add_attribute("Execute auction closed", "OK"))
+}
@@ -0,0 +1,26 @@
+use crate::error::BidError;
+use crate::factory::msg::FactoryInstantiateMsg;
+use crate::factory::state::{FactoryConfig, FACTORY_CONFIG};
+use cosmwasm_std::{DepsMut, Env, MessageInfo, Response};
+
+pub fn _instantiate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: FactoryInstantiateMsg,
+) -> Result<Response, BidError> {
+    let owner = match msg.owner {
+        Some(owner) => deps.api.addr_validate(&owner)?,
+        None => info.sender,
+    };
+
+    FACTORY_CONFIG.save(
+        deps.storage,
+        &FactoryConfig {
+            owner,
+            bidding_code_id: msg.bidding_code_id,
+        },
+    )?;
+
+    Ok(Response::new().add_attribute("Instantiate", "Instantiate OK"))
+}
@@ -0,0 +1,41 @@
+use crate::factory::state::{AuctionEntry, AuctionStatus, FactoryConfig};
+use crate::msg::BidInstantiateMsg;
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cw20::Denom;
+
+#[cw_serde]
+pub struct FactoryInstantiateMsg {
+    pub owner: Option<String>,
+    // Code of the bidding contract instantiated for every auction
+    pub bidding_code_id: u64,
+}
+
+#[cw_serde]
+pub enum FactoryExecuteMsg {
+    // Instantiates a bidding contract sold by the sender unless its owner is set, funds are
+    // forwarded to it
+    CreateAuction(Box<BidInstantiateMsg>),
+    UpdateCodeId { code_id: u64 },
+    // Sent by a bidding contract of the registry when one of its auctions is closed
+    AuctionClosed { auction_id: u64 },
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum FactoryQueryMsg {
+    #[returns(FactoryConfig)]
+    GetConfig {},
+
+    #[returns(AuctionEntry)]
+    GetAuction { address: String },
+
+    // Sorted by contract address, optionally only the matching auctions
+    #[returns(Vec<AuctionEntry>)]
+    ListAuctions {
+        seller: Option<String>,
+        accepted_token: Option<Denom>,
+        status: Option<AuctionStatus>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
@@ -0,0 +1,77 @@
+use crate::factory::msg::FactoryQueryMsg;
+use crate::factory::state::{AuctionEntry, AuctionStatus, FactoryConfig, AUCTIONS, FACTORY_CONFIG};
+use cosmwasm_std::{to_binary, Binary, Deps, Env, Order, StdResult};
+use cw20::Denom;
+use cw_storage_plus::Bound;
+
+pub fn _query(deps: Deps, _env: Env, msg: FactoryQueryMsg) -> StdResult<Binary> {
+    match msg {
+        FactoryQueryMsg::GetConfig {} => to_binary(&get_config(deps)?),
+        FactoryQueryMsg::GetAuction { address } => to_binary(&get_auction(deps, address)?),
+        FactoryQueryMsg::ListAuctions {
+            seller,
+            accepted_token,
+            status,
+            start_after,
+            limit,
+        } => to_binary(&list_auctions(
+            deps,
+            seller,
+            accepted_token,
+            status,
+            start_after,
+            limit,
+        )?),
+    }
+}
+
+pub fn get_config(deps: Deps) -> StdResult<FactoryConfig> {
+    FACTORY_CONFIG.load(deps.storage)
+}
+
+pub fn get_auction(deps: Deps, address: String) -> StdResult<AuctionEntry> {
+    let address = deps.api.addr_validate(&address)?;
+    AUCTIONS.load(deps.storage, address)
+}
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn list_auctions(
+    deps: Deps,
+    seller: Option<String>,
+    accepted_token: Option<Denom>,
+    status: Option<AuctionStatus>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<AuctionEntry>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after
+        .map(|address| deps.api.addr_validate(&address))
+        .transpose()?;
+    let start = start_after.map(Bound::exclusive);
+    let seller = seller
+        .map(|seller| deps.api.addr_validate(&seller))
+        .transpose()?;
+
+    let mut auctions = vec![];
+    for entry in AUCTIONS.range(deps.storage, start, None, Order::Ascending) {
+        let (_, auction) = entry?;
+        let seller_matches = seller
+            .as_ref()
+            .map_or(true, |seller| &auction.seller == seller);
+        let token_matches = accepted_token.as_ref().map_or(true, |accepted_token| {
+            &auction.accepted_token == accepted_token
+        });
+        let status_matches = status
+            .as_ref()
+            .map_or(true, |status| &auction.status == status);
+        if seller_matches && token_matches && status_matches {
+            auctions.push(auction);
+            if auctions.len() == limit {
+                break;
+            }
+        }
+    }
+    Ok(auctions)
+}
@@ -0,0 +1,34 @@
+use crate::error::BidError;
+use crate::factory::execute::INSTANTIATE_AUCTION_REPLY_ID;
+use crate::factory::state::{AuctionEntry, AuctionStatus, AUCTIONS, PENDING_AUCTION};
+use cosmwasm_std::{DepsMut, Env, Reply, Response, StdError};
+use cw_utils::parse_reply_instantiate_data;
+
+pub fn _reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, BidError> {
+    match msg.id {
+        INSTANTIATE_AUCTION_REPLY_ID => register_auction(deps, msg),
+        id => Err(StdError::generic_err(format!("Unknown reply id {}", id)).into()),
+    }
+}
+
+// Records the address of the bidding contract just instantiated
+pub fn register_auction(deps: DepsMut, msg: Reply) -> Result<Response, BidError> {
+    let address = parse_reply_instantiate_data(msg)?.contract_address;
+    let address = deps.api.addr_validate(&address)?;
+
+    let (seller, accepted_token) = PENDING_AUCTION.load(deps.storage)?;
+    PENDING_AUCTION.remove(deps.storage);
+
+    AUCTIONS.save(
+        deps.storage,
+        address.clone(),
+        &AuctionEntry {
+            address: address.clone(),
+            seller,
+            accepted_token,
+            status: AuctionStatus::Open {},
+        },
+    )?;
+
+    Ok(Response::new().add_attribute("Auction registered", address))
+}
@@ -0,0 +1,32 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::Addr;
+use cw20::Denom;
+use cw_storage_plus::{Item, Map};
+
+#[cw_serde]
+pub struct FactoryConfig {
+    pub owner: Addr,
+    pub bidding_code_id: u64,
+}
+pub const FACTORY_CONFIG: Item<FactoryConfig> = Item::new("factory_config");
+
+#[cw_serde]
+pub enum AuctionStatus {
+    Open {},
+    Closed {},
+}
+
+// Auction created at the instantiation of a bidding contract
+#[cw_serde]
+pub struct AuctionEntry {
+    pub address: Addr,
+    pub seller: Addr,
+    pub accepted_token: Denom,
+    pub status: AuctionStatus,
+}
+
+// Registry keyed by bidding contract address
+pub const AUCTIONS: Map<Addr, AuctionEntry> = Map::new("factory_auctions");
+
+// Seller and token of the auction being instantiated, registered once its address is known
+pub const PENDING_AUCTION: Item<(Addr, Denom)> = Item::new("factory_pending_auction");
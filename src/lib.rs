@@ -1,5 +1,6 @@
 pub mod contract;
 pub mod error;
+pub mod factory;
 pub mod msg;
 mod multitest;
 pub mod state;
@@ -1,38 +1,364 @@
-use crate::state::HighestBid;
+use crate::state::{
+    AntiSniping, AuctionMode, BuyNowPrice, Clearing, Commission, Config, FeeRecipient, HighestBid,
+    Lot, MinIncrement, PendingConfigChange, Phase, Role, Settlement,
+};
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Coin, Uint128};
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Uint128};
+use cw20::{Cw20ReceiveMsg, Denom};
+use cw721::Cw721ReceiveMsg;
+use cw_utils::{Duration, Expiration, Scheduled};
+use sha2::{Digest, Sha256};
 
 #[cw_serde]
 pub struct BidInstantiateMsg {
     pub owner: Option<String>,
-    pub commission: Uint128,
-    pub accepted_token: Coin,
+    pub commission: Commission,
+    pub accepted_token: Denom,
+    pub start: Option<Scheduled>,
+    pub end: Expiration,
+    pub anti_sniping: Option<AntiSniping>,
+    pub reserve_price: Option<Uint128>,
+    pub min_increment: Option<MinIncrement>,
+    // Shares must sum to 100%, empty sends the whole commission to the owner
+    pub fee_recipients: Vec<FeeRecipientMsg>,
+    pub mode: AuctionMode,
+    pub settlement: Settlement,
+    // Open mode only, must not be under the reserve price
+    pub buy_now_price: Option<BuyNowPrice>,
+    // Lot the owner has to deposit before bidding opens
+    pub lot: Option<LotMsg>,
+    // Auction factory told when the auction closes, has to be the instantiator. Refused in
+    // CreateAuction
+    pub factory: Option<String>,
+    // Identical items sold to the highest bidders, open first price auction without lot only.
+    // None sells a single item
+    pub quantity: Option<u32>,
+    // Share of the escrow kept as commission when a bid is withdrawn before close, at most 100%
+    pub withdrawal_penalty: Option<Decimal>,
+    // Delay before a config update takes effect, None applies updates right away
+    pub config_timelock: Option<Duration>,
+}
+
+#[cw_serde]
+pub enum LotMsg {
+    // Deposited by the owner through cw721 SendNft
+    Nft { contract: String, token_id: String },
+    // Sent with the instantiation or DepositLot
+    Native { coin: Coin },
+    // Deposited by the owner through cw20 Send with BidReceiveMsg::DepositLot
+    Cw20 { contract: String, amount: Uint128 },
+}
+
+// Fee settings are changed by the fee managers, the others by the owner. Once bidding started,
+// commission, penalty and reserve price can only go down, the deadline only later and the token
+// stays the same
+#[cw_serde]
+#[derive(Default)]
+pub struct ConfigUpdate {
+    pub commission: Option<Commission>,
+    pub fee_recipients: Option<Vec<FeeRecipientMsg>>,
+    pub withdrawal_penalty: Option<Decimal>,
+    pub accepted_token: Option<Denom>,
+    pub end: Option<Expiration>,
+    pub reserve_price: Option<Uint128>,
+}
+
+impl ConfigUpdate {
+    pub fn changes_fees(&self) -> bool {
+        self.commission.is_some()
+            || self.fee_recipients.is_some()
+            || self.withdrawal_penalty.is_some()
+    }
+
+    pub fn changes_terms(&self) -> bool {
+        self.accepted_token.is_some() || self.end.is_some() || self.reserve_price.is_some()
+    }
+}
+
+#[cw_serde]
+pub struct FeeRecipientMsg {
+    pub address: String,
+    pub share: Decimal,
 }
 
 #[cw_serde]
 pub enum BidExecuteMsg {
-    Bid {},
-    Close {},
-    Retract { receiver: Option<Addr> },
+    // Same parameters as the instantiation, the sender is the owner unless set
+    CreateAuction(Box<BidInstantiateMsg>),
+    Bid {
+        auction_id: u64,
+    },
+    Close {
+        auction_id: u64,
+    },
+    Retract {
+        auction_id: u64,
+        receiver: Option<Addr>,
+    },
+    // Shorthand for UpdateConfig, subject to the same timelock
+    UpdateFeeRecipients {
+        auction_id: u64,
+        recipients: Vec<FeeRecipientMsg>,
+    },
+    // Settings left to None are kept, queued until the config timelock has passed
+    UpdateConfig {
+        auction_id: u64,
+        update: ConfigUpdate,
+    },
+    // Owner only, drops a queued update
+    CancelConfigChange {
+        auction_id: u64,
+        change_id: u64,
+    },
+    // Sealed mode : deposit sent with the commitment has to cover the bid revealed later
+    CommitBid {
+        auction_id: u64,
+        commitment: Binary,
+    },
+    RevealBid {
+        auction_id: u64,
+        amount: Uint128,
+        salt: String,
+    },
+    // Dutch mode : buys at the current price, overpayment is refunded
+    BuyNow {
+        auction_id: u64,
+    },
+    // Uniform mode : escrows quantity times max_price, the difference with the clearing price is
+    // retractable after close. At most 50 batch bids per auction
+    BatchBid {
+        auction_id: u64,
+        quantity: Uint128,
+        max_price: Uint128,
+    },
+    // Reverse mode : undercuts the best offer, the first offer of a supplier comes with the bond
+    Offer {
+        auction_id: u64,
+        price: Uint128,
+    },
+    // Pulls out the escrow of a bid which is not winning, before close
+    Withdraw {
+        auction_id: u64,
+    },
+    // After close, refunds the next `limit` losers, anyone can call it
+    ProcessRefunds {
+        auction_id: u64,
+        limit: Option<u32>,
+    },
+    // Escrows up to `max`, the visible bid is raised automatically to stay the highest
+    ProxyBid {
+        auction_id: u64,
+        max: Uint128,
+    },
+    // Offers the auction to `new_owner`, who has to accept before `expiry`
+    TransferOwnership {
+        auction_id: u64,
+        new_owner: String,
+        expiry: Option<Expiration>,
+    },
+    AcceptOwnership {
+        auction_id: u64,
+    },
+    // Gives up closing early and managing the auction, the sale proceeds still go to the owner
+    RenounceOwnership {
+        auction_id: u64,
+    },
+    // Admins only
+    GrantRole {
+        auction_id: u64,
+        role: Role,
+        address: String,
+    },
+    RevokeRole {
+        auction_id: u64,
+        role: Role,
+        address: String,
+    },
+    // Operators only, new bids are refused until resumed. Not available in sealed and dutch auctions
+    Pause {
+        auction_id: u64,
+    },
+    // Pushes the deadline back by the paused time. Open to anyone once paused for a week
+    Resume {
+        auction_id: u64,
+    },
+    // Native lot deposit by the owner
+    DepositLot {
+        auction_id: u64,
+    },
+    // Lot deposit, sent by the cw721 contract, `msg` is a BidReceiveMsg::DepositLot
+    ReceiveNft(Cw721ReceiveMsg),
+    // Bids paid in the accepted cw20 token, `msg` is a BidReceiveMsg
+    Receive(Cw20ReceiveMsg),
+}
+
+#[cw_serde]
+pub enum BidReceiveMsg {
+    Bid {
+        auction_id: u64,
+    },
+    CommitBid {
+        auction_id: u64,
+        commitment: Binary,
+    },
+    BuyNow {
+        auction_id: u64,
+    },
+    BatchBid {
+        auction_id: u64,
+        quantity: Uint128,
+        max_price: Uint128,
+    },
+    Offer {
+        auction_id: u64,
+        price: Uint128,
+    },
+    ProxyBid {
+        auction_id: u64,
+        max: Uint128,
+    },
+    // Cw20 or cw721 lot deposit by the owner
+    DepositLot {
+        auction_id: u64,
+    },
+}
+
+// Commitment expected by CommitBid : sha256 of bidder address, amount as 16 big endian bytes and
+// salt. The fixed size amount keeps a commitment from opening to another amount and salt split
+pub fn commitment_hash(bidder: &Addr, amount: Uint128, salt: &str) -> Binary {
+    let mut hasher = Sha256::new();
+    hasher.update(bidder.as_bytes());
+    hasher.update(amount.u128().to_be_bytes());
+    hasher.update(salt.as_bytes());
+    Binary::from(hasher.finalize().as_slice())
 }
 
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum BidQueryMsg {
     #[returns(u128)]
-    GetTotalBidAddr { address: Addr },
+    GetTotalBidAddr { auction_id: u64, address: Addr },
 
     #[returns(HighestBid)]
-    GetHighestBid {},
+    GetHighestBid { auction_id: u64 },
 
-    #[returns(Addr)]
-    GetWinningBidder {},
+    #[returns(WinningBidderResponse)]
+    GetWinningBidder { auction_id: u64 },
 
-    #[returns(String)]
-    GetAcceptedDenom {},
+    #[returns(Denom)]
+    GetAcceptedDenom { auction_id: u64 },
 
     #[returns(bool)]
-    IsBiddingClosed {},
+    IsBiddingClosed { auction_id: u64 },
+
+    #[returns(BiddingWindowResponse)]
+    GetBiddingWindow { auction_id: u64 },
+
+    // Total bid needed, in multi-winner mode to rank among the winners. `bidder` is left out of
+    // the ranking when set
+    #[returns(Uint128)]
+    GetMinimumNextBid {
+        auction_id: u64,
+        bidder: Option<String>,
+    },
+
+    #[returns(Vec<FeeRecipient>)]
+    GetFeeRecipients { auction_id: u64 },
+
+    #[returns(Phase)]
+    GetPhase { auction_id: u64 },
+
+    #[returns(Uint128)]
+    GetRunnerUpBid { auction_id: u64 },
+
+    #[returns(Uint128)]
+    GetCurrentPrice { auction_id: u64 },
+
+    // None when there is no buy-now price, or it has been disabled
+    #[returns(Option<Uint128>)]
+    GetBuyNowPrice { auction_id: u64 },
+
+    #[returns(LotResponse)]
+    GetLot { auction_id: u64 },
+
+    // Uniform mode : clearing price and fills if the auction closed now
+    #[returns(Clearing)]
+    SimulateClearing { auction_id: u64 },
+
+    // Losers the batch refund still has to pay back, once closed
+    #[returns(u32)]
+    GetPendingRefunds { auction_id: u64 },
+
+    // Queued config updates, applied by the first bid or close once effective
+    #[returns(Vec<PendingConfigChange>)]
+    GetPendingConfigChanges { auction_id: u64 },
+
+    #[returns(OwnershipResponse)]
+    GetOwnership { auction_id: u64 },
+
+    // Addresses granted `role`, the owner excluded
+    #[returns(Vec<Addr>)]
+    GetRoleMembers { auction_id: u64, role: Role },
+
+    #[returns(bool)]
+    IsPaused { auction_id: u64 },
+
+    #[returns(AuctionResponse)]
+    GetAuction { auction_id: u64 },
+
+    // Sorted by id, optionally only the auctions in `status` or sold by `seller`
+    #[returns(Vec<AuctionResponse>)]
+    ListAuctions {
+        status: Option<Phase>,
+        seller: Option<String>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+pub enum WinningBidderResponse {
+    NoWinnerYet {},
+    Winner { address: Addr },
+    // Multi-winner mode, sorted from the highest bid
+    Winners { addresses: Vec<Addr> },
+    // Closed with the highest bid under the reserve price
+    NoSale {},
+}
+
+#[cw_serde]
+pub struct BiddingWindowResponse {
+    pub start: Option<Scheduled>,
+    // current deadline, anti-sniping extensions included
+    pub end: Expiration,
+    // None when the auction has no deadline
+    pub time_remaining: Option<Duration>,
+    pub anti_sniping: Option<AntiSniping>,
+    pub extensions: u32,
+}
+
+#[cw_serde]
+pub struct AuctionResponse {
+    pub auction_id: u64,
+    pub config: Config,
+    pub phase: Phase,
+    pub highest_bid: HighestBid,
+}
+
+#[cw_serde]
+pub struct LotResponse {
+    // None when the auction only records the winner
+    pub lot: Option<Lot>,
+    // true while the contract holds the lot
+    pub escrowed: bool,
+}
+
+#[cw_serde]
+pub struct OwnershipResponse {
+    // None once renounced
+    pub owner: Option<Addr>,
+    pub pending_owner: Option<Addr>,
+    pub pending_expiry: Option<Expiration>,
 }
 
 #[cw_serde]
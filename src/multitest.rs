@@ -1,17 +1,76 @@
 #![cfg(test)]
 
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, migrate, query, reply};
 use crate::error::BidError;
-use crate::msg::{BidExecuteMsg, BidInstantiateMsg, BidQueryMsg};
-use crate::state::HighestBid;
-use cosmwasm_std::{coin, coins, Addr, Empty, Uint128};
+use crate::factory;
+use crate::factory::msg::{FactoryExecuteMsg, FactoryInstantiateMsg, FactoryQueryMsg};
+use crate::factory::state::{AuctionEntry, AuctionStatus};
+use crate::msg::{
+    commitment_hash, AuctionResponse, BidExecuteMsg, BidInstantiateMsg, BidMigrateMsg, BidQueryMsg,
+    BidReceiveMsg, BiddingWindowResponse, ConfigUpdate, FeeRecipientMsg, LotMsg, LotResponse,
+    OwnershipResponse, WinningBidderResponse,
+};
+use crate::state::{
+    AntiSniping, AuctionMode, BuyNowPrice, Clearing, Commission, FeeRecipient, HighestBid, Lot,
+    MinIncrement, PendingConfigChange, Phase, PriceDecline, Role, Settlement,
+};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    coin, coins, to_binary, Addr, Binary, Coin, Decimal, Deps, DepsMut, Empty, Env, Event,
+    MessageInfo, Response, StdResult, Uint128,
+};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg, Denom};
 use cw_multi_test::{App, Contract, ContractWrapper, Executor};
+use cw_storage_plus::{Item, Map};
+use cw_utils::{Duration, Expiration, Scheduled};
 
 fn bidding_contract() -> Box<dyn Contract<Empty>> {
-    let contract = ContractWrapper::new(execute, instantiate, query);
+    let contract = ContractWrapper::new(execute, instantiate, query).with_reply(reply);
     Box::new(contract)
 }
 
+fn cw20_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    );
+    Box::new(contract)
+}
+
+fn cw721_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        cw721_base::entry::execute,
+        cw721_base::entry::instantiate,
+        cw721_base::entry::query,
+    );
+    Box::new(contract)
+}
+
+fn default_instantiate_msg() -> BidInstantiateMsg {
+    BidInstantiateMsg {
+        owner: Some("owner".to_string()),
+        commission: Commission::FlatPerBid {
+            amount: Uint128::from(1u64),
+        },
+        accepted_token: Denom::Native("atom".to_string()),
+        start: None,
+        end: Expiration::Never {},
+        anti_sniping: None,
+        reserve_price: None,
+        min_increment: None,
+        fee_recipients: vec![],
+        mode: AuctionMode::Open {},
+        settlement: Settlement::FirstPrice {},
+        buy_now_price: None,
+        lot: None,
+        factory: None,
+        quantity: None,
+        withdrawal_penalty: None,
+        config_timelock: None,
+    }
+}
+
 #[test]
 fn test_execute_full_workflow() {
     // Scenario : 3 bidders
@@ -53,9 +112,10 @@ fn test_execute_full_workflow() {
             contract_id,
             Addr::unchecked("instantiator"),
             &BidInstantiateMsg {
-                owner: Some("owner".to_string()),
-                commission: Uint128::from(2u64),
-                accepted_token: coin(0, "atom"),
+                commission: Commission::FlatPerBid {
+                    amount: Uint128::from(2u64),
+                },
+                ..default_instantiate_msg()
             },
             &[],
             "Bidding Contract",
@@ -67,7 +127,7 @@ fn test_execute_full_workflow() {
         .execute_contract(
             Addr::unchecked("owner"),
             contract_addr.clone(),
-            &BidExecuteMsg::Bid {},
+            &BidExecuteMsg::Bid { auction_id: 0 },
             &coins(5, "atom"),
         )
         .unwrap_err();
@@ -78,7 +138,7 @@ fn test_execute_full_workflow() {
         .execute_contract(
             Addr::unchecked("baddenom"),
             contract_addr.clone(),
-            &BidExecuteMsg::Bid {},
+            &BidExecuteMsg::Bid { auction_id: 0 },
             &coins(5, "notatom"),
         )
         .unwrap_err();
@@ -89,7 +149,7 @@ fn test_execute_full_workflow() {
         .execute_contract(
             Addr::unchecked("bidderpoor"),
             contract_addr.clone(),
-            &BidExecuteMsg::Bid {},
+            &BidExecuteMsg::Bid { auction_id: 0 },
             &coins(1, "atom"),
         )
         .unwrap_err();
@@ -103,7 +163,7 @@ fn test_execute_full_workflow() {
     app.execute_contract(
         Addr::unchecked("bidder1"),
         contract_addr.clone(),
-        &BidExecuteMsg::Bid {},
+        &BidExecuteMsg::Bid { auction_id: 0 },
         &coins(10, "atom"),
     )
     .unwrap();
@@ -113,6 +173,7 @@ fn test_execute_full_workflow() {
         .query_wasm_smart(
             contract_addr.clone(),
             &BidQueryMsg::GetTotalBidAddr {
+                auction_id: 0,
                 address: Addr::unchecked("bidder1"),
             },
         )
@@ -122,7 +183,10 @@ fn test_execute_full_workflow() {
 
     let resp: HighestBid = app
         .wrap()
-        .query_wasm_smart(contract_addr.clone(), &BidQueryMsg::GetHighestBid {})
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetHighestBid { auction_id: 0 },
+        )
         .unwrap();
 
     assert_eq!(
@@ -139,7 +203,7 @@ fn test_execute_full_workflow() {
         .execute_contract(
             Addr::unchecked("bidder2"),
             contract_addr.clone(),
-            &BidExecuteMsg::Bid {},
+            &BidExecuteMsg::Bid { auction_id: 0 },
             &coins(10, "atom"),
         )
         .unwrap_err();
@@ -151,7 +215,7 @@ fn test_execute_full_workflow() {
     app.execute_contract(
         Addr::unchecked("bidder2"),
         contract_addr.clone(),
-        &BidExecuteMsg::Bid {},
+        &BidExecuteMsg::Bid { auction_id: 0 },
         &coins(20, "atom"),
     )
     .unwrap();
@@ -161,6 +225,7 @@ fn test_execute_full_workflow() {
         .query_wasm_smart(
             contract_addr.clone(),
             &BidQueryMsg::GetTotalBidAddr {
+                auction_id: 0,
                 address: Addr::unchecked("bidder2"),
             },
         )
@@ -170,7 +235,10 @@ fn test_execute_full_workflow() {
 
     let resp: HighestBid = app
         .wrap()
-        .query_wasm_smart(contract_addr.clone(), &BidQueryMsg::GetHighestBid {})
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetHighestBid { auction_id: 0 },
+        )
         .unwrap();
 
     assert_eq!(
@@ -184,7 +252,7 @@ fn test_execute_full_workflow() {
     app.execute_contract(
         Addr::unchecked("bidder3"),
         contract_addr.clone(),
-        &BidExecuteMsg::Bid {},
+        &BidExecuteMsg::Bid { auction_id: 0 },
         &coins(25, "atom"),
     )
     .unwrap();
@@ -194,6 +262,7 @@ fn test_execute_full_workflow() {
         .query_wasm_smart(
             contract_addr.clone(),
             &BidQueryMsg::GetTotalBidAddr {
+                auction_id: 0,
                 address: Addr::unchecked("bidder3"),
             },
         )
@@ -203,7 +272,10 @@ fn test_execute_full_workflow() {
 
     let resp: HighestBid = app
         .wrap()
-        .query_wasm_smart(contract_addr.clone(), &BidQueryMsg::GetHighestBid {})
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetHighestBid { auction_id: 0 },
+        )
         .unwrap();
 
     assert_eq!(
@@ -219,7 +291,7 @@ fn test_execute_full_workflow() {
     app.execute_contract(
         Addr::unchecked("bidder1"),
         contract_addr.clone(),
-        &BidExecuteMsg::Bid {},
+        &BidExecuteMsg::Bid { auction_id: 0 },
         &coins(20, "atom"),
     )
     .unwrap();
@@ -229,6 +301,7 @@ fn test_execute_full_workflow() {
         .query_wasm_smart(
             contract_addr.clone(),
             &BidQueryMsg::GetTotalBidAddr {
+                auction_id: 0,
                 address: Addr::unchecked("bidder1"),
             },
         )
@@ -238,7 +311,10 @@ fn test_execute_full_workflow() {
 
     let resp: HighestBid = app
         .wrap()
-        .query_wasm_smart(contract_addr.clone(), &BidQueryMsg::GetHighestBid {})
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetHighestBid { auction_id: 0 },
+        )
         .unwrap();
 
     assert_eq!(
@@ -254,7 +330,7 @@ fn test_execute_full_workflow() {
     app.execute_contract(
         Addr::unchecked("bidder3"),
         contract_addr.clone(),
-        &BidExecuteMsg::Bid {},
+        &BidExecuteMsg::Bid { auction_id: 0 },
         &coins(20, "atom"),
     )
     .unwrap();
@@ -264,6 +340,7 @@ fn test_execute_full_workflow() {
         .query_wasm_smart(
             contract_addr.clone(),
             &BidQueryMsg::GetTotalBidAddr {
+                auction_id: 0,
                 address: Addr::unchecked("bidder3"),
             },
         )
@@ -273,7 +350,10 @@ fn test_execute_full_workflow() {
 
     let resp: HighestBid = app
         .wrap()
-        .query_wasm_smart(contract_addr.clone(), &BidQueryMsg::GetHighestBid {})
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetHighestBid { auction_id: 0 },
+        )
         .unwrap();
 
     assert_eq!(
@@ -299,14 +379,17 @@ fn test_execute_full_workflow() {
     app.execute_contract(
         Addr::unchecked("owner"),
         contract_addr.clone(),
-        &BidExecuteMsg::Close {},
+        &BidExecuteMsg::Close { auction_id: 0 },
         &[],
     )
     .unwrap();
 
     let resp: bool = app
         .wrap()
-        .query_wasm_smart(contract_addr.clone(), &BidQueryMsg::IsBiddingClosed {})
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::IsBiddingClosed { auction_id: 0 },
+        )
         .unwrap();
     assert!(resp);
 
@@ -316,7 +399,7 @@ fn test_execute_full_workflow() {
         .execute_contract(
             Addr::unchecked("bidder1"),
             contract_addr.clone(),
-            &BidExecuteMsg::Bid {},
+            &BidExecuteMsg::Bid { auction_id: 0 },
             &coins(5, "atom"),
         )
         .unwrap_err();
@@ -329,7 +412,10 @@ fn test_execute_full_workflow() {
 
     let resp: HighestBid = app
         .wrap()
-        .query_wasm_smart(contract_addr.clone(), &BidQueryMsg::GetHighestBid {})
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetHighestBid { auction_id: 0 },
+        )
         .unwrap();
 
     assert_eq!(
@@ -377,7 +463,10 @@ fn test_execute_full_workflow() {
     app.execute_contract(
         Addr::unchecked("bidder1"),
         contract_addr.clone(),
-        &BidExecuteMsg::Retract { receiver: None },
+        &BidExecuteMsg::Retract {
+            auction_id: 0,
+            receiver: None,
+        },
         &[],
     )
     .unwrap();
@@ -397,6 +486,7 @@ fn test_execute_full_workflow() {
         Addr::unchecked("bidder2"),
         contract_addr.clone(),
         &BidExecuteMsg::Retract {
+            auction_id: 0,
             receiver: Some(Addr::unchecked("bidderpoor")),
         },
         &[],
@@ -423,7 +513,10 @@ fn test_execute_full_workflow() {
         .execute_contract(
             Addr::unchecked("bidder3"),
             contract_addr,
-            &BidExecuteMsg::Retract { receiver: None },
+            &BidExecuteMsg::Retract {
+                auction_id: 0,
+                receiver: None,
+            },
             &[],
         )
         .unwrap_err();
@@ -442,9 +535,10 @@ fn test_query_bidding_closed() {
             contract_id,
             Addr::unchecked("instantiator"),
             &BidInstantiateMsg {
-                owner: Some("owner".to_string()),
-                commission: Uint128::from(5u64),
-                accepted_token: coin(0, "atom"),
+                commission: Commission::FlatPerBid {
+                    amount: Uint128::from(5u64),
+                },
+                ..default_instantiate_msg()
             },
             &[],
             "Bidding Contract",
@@ -454,7 +548,10 @@ fn test_query_bidding_closed() {
 
     let resp: bool = app
         .wrap()
-        .query_wasm_smart(contract_addr.clone(), &BidQueryMsg::IsBiddingClosed {})
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::IsBiddingClosed { auction_id: 0 },
+        )
         .unwrap();
     assert!(!resp);
 
@@ -462,7 +559,7 @@ fn test_query_bidding_closed() {
         .execute_contract(
             Addr::unchecked("notowner"),
             contract_addr.clone(),
-            &BidExecuteMsg::Close {},
+            &BidExecuteMsg::Close { auction_id: 0 },
             &[],
         )
         .unwrap_err();
@@ -472,14 +569,17 @@ fn test_query_bidding_closed() {
     app.execute_contract(
         Addr::unchecked("owner"),
         contract_addr.clone(),
-        &BidExecuteMsg::Close {},
+        &BidExecuteMsg::Close { auction_id: 0 },
         &[],
     )
     .unwrap();
 
     let resp: bool = app
         .wrap()
-        .query_wasm_smart(contract_addr, &BidQueryMsg::IsBiddingClosed {})
+        .query_wasm_smart(
+            contract_addr,
+            &BidQueryMsg::IsBiddingClosed { auction_id: 0 },
+        )
         .unwrap();
     assert!(resp);
 }
@@ -495,9 +595,10 @@ fn test_query_get_denom() {
             contract_id,
             Addr::unchecked("instantiator"),
             &BidInstantiateMsg {
-                owner: Some("owner".to_string()),
-                commission: Uint128::from(5u64),
-                accepted_token: coin(0, "atom"),
+                commission: Commission::FlatPerBid {
+                    amount: Uint128::from(5u64),
+                },
+                ..default_instantiate_msg()
             },
             &[],
             "Bidding Contract",
@@ -505,11 +606,14 @@ fn test_query_get_denom() {
         )
         .unwrap();
 
-    let resp: String = app
+    let resp: Denom = app
         .wrap()
-        .query_wasm_smart(contract_addr, &BidQueryMsg::GetAcceptedDenom {})
+        .query_wasm_smart(
+            contract_addr,
+            &BidQueryMsg::GetAcceptedDenom { auction_id: 0 },
+        )
         .unwrap();
-    assert_eq!("atom".to_string(), resp);
+    assert_eq!(Denom::Native("atom".to_string()), resp);
 }
 
 #[test]
@@ -523,9 +627,10 @@ fn test_query_get_winning_bidder_instant_close() {
             contract_id,
             Addr::unchecked("instantiator"),
             &BidInstantiateMsg {
-                owner: Some("owner".to_string()),
-                commission: Uint128::from(5u64),
-                accepted_token: coin(0, "atom"),
+                commission: Commission::FlatPerBid {
+                    amount: Uint128::from(5u64),
+                },
+                ..default_instantiate_msg()
             },
             &[],
             "Bidding Contract",
@@ -533,27 +638,33 @@ fn test_query_get_winning_bidder_instant_close() {
         )
         .unwrap();
 
-    let resp: Addr = app
+    let resp: WinningBidderResponse = app
         .wrap()
-        .query_wasm_smart(contract_addr.clone(), &BidQueryMsg::GetWinningBidder {})
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetWinningBidder { auction_id: 0 },
+        )
         .unwrap();
 
-    assert_eq!(resp, Addr::unchecked("nowinneryet"));
+    assert_eq!(resp, WinningBidderResponse::NoWinnerYet {});
 
     app.execute_contract(
         Addr::unchecked("owner"),
         contract_addr.clone(),
-        &BidExecuteMsg::Close {},
+        &BidExecuteMsg::Close { auction_id: 0 },
         &[],
     )
     .unwrap();
 
-    let resp: Addr = app
+    let resp: WinningBidderResponse = app
         .wrap()
-        .query_wasm_smart(contract_addr, &BidQueryMsg::GetWinningBidder {})
+        .query_wasm_smart(
+            contract_addr,
+            &BidQueryMsg::GetWinningBidder { auction_id: 0 },
+        )
         .unwrap();
 
-    assert_eq!(resp, Addr::unchecked("instantiator"));
+    assert_eq!(resp, WinningBidderResponse::NoSale {});
 }
 
 #[test]
@@ -572,9 +683,10 @@ fn test_query_get_winning_bidder_bid_and_close() {
             contract_id,
             Addr::unchecked("instantiator"),
             &BidInstantiateMsg {
-                owner: Some("owner".to_string()),
-                commission: Uint128::from(1u64),
-                accepted_token: coin(0, "atom"),
+                commission: Commission::FlatPerBid {
+                    amount: Uint128::from(1u64),
+                },
+                ..default_instantiate_msg()
             },
             &[],
             "Bidding Contract",
@@ -585,7 +697,7 @@ fn test_query_get_winning_bidder_bid_and_close() {
     app.execute_contract(
         Addr::unchecked("bidder"),
         contract_addr.clone(),
-        &BidExecuteMsg::Bid {},
+        &BidExecuteMsg::Bid { auction_id: 0 },
         &coins(4, "atom"),
     )
     .unwrap();
@@ -593,15 +705,4840 @@ fn test_query_get_winning_bidder_bid_and_close() {
     app.execute_contract(
         Addr::unchecked("owner"),
         contract_addr.clone(),
-        &BidExecuteMsg::Close {},
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    let resp: WinningBidderResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr,
+            &BidQueryMsg::GetWinningBidder { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert_eq!(
+        resp,
+        WinningBidderResponse::Winner {
+            address: Addr::unchecked("bidder")
+        }
+    );
+}
+
+#[test]
+fn test_time_bound_auction() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder"), coins(10, "atom"))
+            .unwrap()
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let now = app.block_info().time;
+
+    // An auction ending before it starts is refused
+    let err = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &BidInstantiateMsg {
+                start: Some(Scheduled::AtTime(now.plus_seconds(1000))),
+                end: Expiration::AtTime(now.plus_seconds(100)),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::InvalidBiddingWindow {}, err.downcast().unwrap());
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &BidInstantiateMsg {
+                start: Some(Scheduled::AtTime(now.plus_seconds(100))),
+                end: Expiration::AtTime(now.plus_seconds(1000)),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    // Bid before start : error
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(4, "atom"),
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::BiddingNotStarted {}, err.downcast().unwrap());
+
+    app.update_block(|block| block.time = block.time.plus_seconds(400));
+
+    app.execute_contract(
+        Addr::unchecked("bidder"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(4, "atom"),
+    )
+    .unwrap();
+
+    let resp: BiddingWindowResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetBiddingWindow { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert_eq!(
+        resp,
+        BiddingWindowResponse {
+            start: Some(Scheduled::AtTime(now.plus_seconds(100))),
+            end: Expiration::AtTime(now.plus_seconds(1000)),
+            time_remaining: Some(Duration::Time(600)),
+            anti_sniping: None,
+            extensions: 0,
+        }
+    );
+
+    // Only the owner can close before the deadline
+    let err = app
+        .execute_contract(
+            Addr::unchecked("anyone"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Close { auction_id: 0 },
+            &[],
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+
+    app.update_block(|block| block.time = block.time.plus_seconds(600));
+
+    // Bid after the deadline : error
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(5, "atom"),
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::BiddingExpired {}, err.downcast().unwrap());
+
+    // Anyone can close once the deadline has passed
+    app.execute_contract(
+        Addr::unchecked("anyone"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 0 },
         &[],
     )
     .unwrap();
 
-    let resp: Addr = app
+    let resp: WinningBidderResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetWinningBidder { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert_eq!(
+        resp,
+        WinningBidderResponse::Winner {
+            address: Addr::unchecked("bidder")
+        }
+    );
+
+    // 4 atoms bid - 1 commission already paid
+    assert_eq!(
+        app.wrap()
+            .query_all_balances(Addr::unchecked("owner"))
+            .unwrap(),
+        coins(4, "atom")
+    );
+
+    // Can not close twice
+    let err = app
+        .execute_contract(
+            Addr::unchecked("anyone"),
+            contract_addr,
+            &BidExecuteMsg::Close { auction_id: 0 },
+            &[],
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::BiddingClosed {}, err.downcast().unwrap());
+}
+
+#[test]
+fn test_anti_sniping_extension() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(20, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder2"), coins(20, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let now = app.block_info().time;
+
+    // Anti-sniping on an auction without deadline is refused
+    let err = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &BidInstantiateMsg {
+                anti_sniping: Some(AntiSniping {
+                    window: Duration::Time(100),
+                    extension: Duration::Time(300),
+                    max_extensions: 2,
+                }),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::InvalidAntiSniping {}, err.downcast().unwrap());
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &BidInstantiateMsg {
+                end: Expiration::AtTime(now.plus_seconds(1000)),
+                anti_sniping: Some(AntiSniping {
+                    window: Duration::Time(100),
+                    extension: Duration::Time(300),
+                    max_extensions: 2,
+                }),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    // Bid outside of the window : no extension
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(2, "atom"),
+        )
+        .unwrap();
+
+    assert!(!resp.has_event(&Event::new("wasm").add_attribute("Bidding extended", "OK")));
+
+    // Bid in the last 100 seconds : end moves from 1000 to 1300
+    app.update_block(|block| block.time = now.plus_seconds(950));
+
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("bidder2"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(3, "atom"),
+        )
+        .unwrap();
+
+    assert!(resp.has_event(&Event::new("wasm").add_attribute(
+        "New end",
+        Expiration::AtTime(now.plus_seconds(1300)).to_string()
+    )));
+
+    // Second and last extension : end moves to 1600
+    app.update_block(|block| block.time = now.plus_seconds(1250));
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(2, "atom"),
+    )
+    .unwrap();
+
+    // Max extensions reached : the deadline does not move anymore
+    app.update_block(|block| block.time = now.plus_seconds(1550));
+
+    app.execute_contract(
+        Addr::unchecked("bidder2"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(3, "atom"),
+    )
+    .unwrap();
+
+    let resp: BiddingWindowResponse = app
         .wrap()
-        .query_wasm_smart(contract_addr, &BidQueryMsg::GetWinningBidder {})
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetBiddingWindow { auction_id: 0 },
+        )
         .unwrap();
 
-    assert_eq!(resp, Addr::unchecked("bidder"));
+    assert_eq!(resp.end, Expiration::AtTime(now.plus_seconds(1600)));
+    assert_eq!(resp.time_remaining, Some(Duration::Time(50)));
+    assert_eq!(resp.extensions, 2);
+
+    app.update_block(|block| block.time = now.plus_seconds(1600));
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr,
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(5, "atom"),
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::BiddingExpired {}, err.downcast().unwrap());
+}
+
+#[test]
+fn test_reserve_price_no_sale() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(10, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder2"), coins(10, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &BidInstantiateMsg {
+                reserve_price: Some(Uint128::from(20u128)),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(5, "atom"),
+    )
+    .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("bidder2"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    let resp: WinningBidderResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetWinningBidder { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert_eq!(resp, WinningBidderResponse::NoSale {});
+
+    // Owner only got the 2 commissions
+    assert_eq!(
+        app.wrap()
+            .query_all_balances(Addr::unchecked("owner"))
+            .unwrap(),
+        coins(2, "atom")
+    );
+
+    // Highest bidder can retract too : 10 - 1 commission
+    app.execute_contract(
+        Addr::unchecked("bidder2"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Retract {
+            auction_id: 0,
+            receiver: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(
+        app.wrap()
+            .query_all_balances(Addr::unchecked("bidder2"))
+            .unwrap(),
+        coins(9, "atom")
+    );
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr,
+        &BidExecuteMsg::Retract {
+            auction_id: 0,
+            receiver: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(
+        app.wrap()
+            .query_all_balances(Addr::unchecked("bidder1"))
+            .unwrap(),
+        coins(9, "atom")
+    );
+}
+
+#[test]
+fn test_minimum_bid_increment() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(30, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder2"), coins(30, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &BidInstantiateMsg {
+                min_increment: Some(MinIncrement {
+                    absolute: Some(Uint128::from(3u128)),
+                    percentage: Some(Decimal::percent(20)),
+                }),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    // 10 + max(3, 20% of 10)
+    let resp: Uint128 = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetMinimumNextBid {
+                auction_id: 0,
+                bidder: None,
+            },
+        )
+        .unwrap();
+
+    assert_eq!(resp, Uint128::from(13u128));
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder2"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(12, "atom"),
+        )
+        .unwrap_err();
+
+    assert_eq!(
+        BidError::BidUnderMinimumIncrement {
+            minimum: Uint128::from(13u128)
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        Addr::unchecked("bidder2"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(13, "atom"),
+    )
+    .unwrap();
+
+    // 13 + max(3, 20% of 13) : bidder1 needs 6 more atoms on top of his 10
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(5, "atom"),
+        )
+        .unwrap_err();
+
+    assert_eq!(
+        BidError::BidUnderMinimumIncrement {
+            minimum: Uint128::from(16u128)
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(6, "atom"),
+    )
+    .unwrap();
+
+    let resp: HighestBid = app
+        .wrap()
+        .query_wasm_smart(contract_addr, &BidQueryMsg::GetHighestBid { auction_id: 0 })
+        .unwrap();
+
+    assert_eq!(
+        resp,
+        HighestBid {
+            address: Addr::unchecked("bidder1"),
+            bid: Uint128::from(16u128)
+        }
+    );
+}
+
+#[test]
+fn test_percentage_commission() {
+    // Owner balance before close, after close, and bidder1 refund
+    // Only the winner pays : full refund for bidder1
+    // Every bid pays : owner gets 10% of bidder1 bid that is not refunded
+    for (commission, owner_before_close, owner_after_close, bidder1_refund) in [
+        (
+            Commission::PercentOfWinningBid {
+                rate: Decimal::percent(10),
+            },
+            0u128,
+            20u128,
+            10u128,
+        ),
+        (
+            Commission::PercentOfEachBid {
+                rate: Decimal::percent(10),
+            },
+            3u128,
+            21u128,
+            9u128,
+        ),
+    ] {
+        let mut app = App::new(|router, _api, storage| {
+            router
+                .bank
+                .init_balance(storage, &Addr::unchecked("bidder1"), coins(10, "atom"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &Addr::unchecked("bidder2"), coins(20, "atom"))
+                .unwrap();
+        });
+
+        let contract_id = app.store_code(bidding_contract());
+
+        let contract_addr = app
+            .instantiate_contract(
+                contract_id,
+                Addr::unchecked("instantiator"),
+                &BidInstantiateMsg {
+                    commission,
+                    ..default_instantiate_msg()
+                },
+                &[],
+                "Bidding Contract",
+                None,
+            )
+            .unwrap();
+
+        app.execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(10, "atom"),
+        )
+        .unwrap();
+
+        app.execute_contract(
+            Addr::unchecked("bidder2"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(20, "atom"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked("owner"), "atom")
+                .unwrap()
+                .amount,
+            Uint128::from(owner_before_close)
+        );
+
+        app.execute_contract(
+            Addr::unchecked("owner"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Close { auction_id: 0 },
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked("owner"), "atom")
+                .unwrap()
+                .amount,
+            Uint128::from(owner_after_close)
+        );
+
+        app.execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr,
+            &BidExecuteMsg::Retract {
+                auction_id: 0,
+                receiver: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked("bidder1"), "atom")
+                .unwrap()
+                .amount,
+            Uint128::from(bidder1_refund)
+        );
+    }
+}
+
+#[test]
+fn test_commission_split_across_fee_recipients() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(20, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder2"), coins(30, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let fee_recipients = vec![
+        FeeRecipientMsg {
+            address: "treasury".to_string(),
+            share: Decimal::percent(50),
+        },
+        FeeRecipientMsg {
+            address: "referrer".to_string(),
+            share: Decimal::percent(30),
+        },
+        FeeRecipientMsg {
+            address: "charity".to_string(),
+            share: Decimal::percent(20),
+        },
+    ];
+
+    // Shares not summing to 100% are refused
+    let err = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &BidInstantiateMsg {
+                fee_recipients: fee_recipients[..2].to_vec(),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::InvalidFeeRecipients {}, err.downcast().unwrap());
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &BidInstantiateMsg {
+                commission: Commission::FlatPerBid {
+                    amount: Uint128::from(7u128),
+                },
+                fee_recipients,
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    // 7 atoms : 3 + 2 + 1, and the rounding dust goes to the treasury
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(20, "atom"),
+    )
+    .unwrap();
+
+    for (recipient, balance) in [("treasury", 4u128), ("referrer", 2u128), ("charity", 1u128)] {
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(recipient), "atom")
+                .unwrap()
+                .amount,
+            Uint128::from(balance)
+        );
+    }
+
+    let resp: Vec<FeeRecipient> = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetFeeRecipients { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert_eq!(resp.len(), 3);
+    assert_eq!(
+        resp[0],
+        FeeRecipient {
+            address: Addr::unchecked("treasury"),
+            share: Decimal::percent(50)
+        }
+    );
+
+    let new_fee_recipients = vec![FeeRecipientMsg {
+        address: "charity".to_string(),
+        share: Decimal::one(),
+    }];
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::UpdateFeeRecipients {
+                auction_id: 0,
+                recipients: new_fee_recipients.clone(),
+            },
+            &[],
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &BidExecuteMsg::UpdateFeeRecipients {
+            auction_id: 0,
+            recipients: new_fee_recipients,
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("bidder2"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(30, "atom"),
+    )
+    .unwrap();
+
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("charity"), "atom")
+            .unwrap()
+            .amount,
+        Uint128::from(8u128)
+    );
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr,
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    // Owner only gets the winning bid minus the commission paid on it
+    assert_eq!(
+        app.wrap()
+            .query_all_balances(Addr::unchecked("owner"))
+            .unwrap(),
+        coins(23, "atom")
+    );
+}
+
+#[test]
+fn test_sealed_bid_commit_reveal() {
+    let mut app = App::new(|router, _api, storage| {
+        for bidder in ["bidder1", "bidder2", "bidder3"] {
+            router
+                .bank
+                .init_balance(storage, &Addr::unchecked(bidder), coins(100, "atom"))
+                .unwrap();
+        }
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let now = app.block_info().time;
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &BidInstantiateMsg {
+                end: Expiration::AtTime(now.plus_seconds(100)),
+                mode: AuctionMode::Sealed {
+                    reveal_end: Expiration::AtTime(now.plus_seconds(200)),
+                    slash_unrevealed: true,
+                },
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    let resp: Phase = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetPhase { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert_eq!(resp, Phase::Commit {});
+
+    // Public bids are refused
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(10, "atom"),
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::WrongAuctionMode {}, err.downcast().unwrap());
+
+    // Deposits hide the real bids : 30, 40, and bidder3 never reveals
+    for (bidder, amount, salt, deposit) in [
+        ("bidder1", 30u128, "salt1", 50u128),
+        ("bidder2", 40u128, "salt2", 45u128),
+        ("bidder3", 60u128, "salt3", 60u128),
+    ] {
+        app.execute_contract(
+            Addr::unchecked(bidder),
+            contract_addr.clone(),
+            &BidExecuteMsg::CommitBid {
+                auction_id: 0,
+                commitment: commitment_hash(&Addr::unchecked(bidder), amount.into(), salt),
+            },
+            &coins(deposit, "atom"),
+        )
+        .unwrap();
+    }
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::RevealBid {
+                auction_id: 0,
+                amount: Uint128::from(30u128),
+                salt: "salt1".to_string(),
+            },
+            &[],
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::NotRevealPhase {}, err.downcast().unwrap());
+
+    // Even the owner has to wait for the reveals
+    let err = app
+        .execute_contract(
+            Addr::unchecked("owner"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Close { auction_id: 0 },
+            &[],
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::BiddingNotOver {}, err.downcast().unwrap());
+
+    app.update_block(|block| block.time = now.plus_seconds(150));
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::RevealBid {
+                auction_id: 0,
+                amount: Uint128::from(30u128),
+                salt: "wrongsalt".to_string(),
+            },
+            &[],
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::InvalidReveal {}, err.downcast().unwrap());
+
+    // Moving digits between amount and salt does not open the commitment
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::RevealBid {
+                auction_id: 0,
+                amount: Uint128::from(3u128),
+                salt: "0salt1".to_string(),
+            },
+            &[],
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::InvalidReveal {}, err.downcast().unwrap());
+
+    for (bidder, amount, salt) in [("bidder1", 30u128, "salt1"), ("bidder2", 40u128, "salt2")] {
+        app.execute_contract(
+            Addr::unchecked(bidder),
+            contract_addr.clone(),
+            &BidExecuteMsg::RevealBid {
+                auction_id: 0,
+                amount: amount.into(),
+                salt: salt.to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
+    let resp: HighestBid = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetHighestBid { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert_eq!(
+        resp,
+        HighestBid {
+            address: Addr::unchecked("bidder2"),
+            bid: Uint128::from(40u128)
+        }
+    );
+
+    app.update_block(|block| block.time = now.plus_seconds(250));
+
+    app.execute_contract(
+        Addr::unchecked("anyone"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    // 3 commissions + 40 - 1 from the winner + 60 - 1 slashed from bidder3
+    assert_eq!(
+        app.wrap()
+            .query_all_balances(Addr::unchecked("owner"))
+            .unwrap(),
+        coins(101, "atom")
+    );
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder3"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Retract {
+                auction_id: 0,
+                receiver: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::DepositSlashed {}, err.downcast().unwrap());
+
+    // Loser gets his deposit back, winner what he deposited over his bid
+    for (bidder, balance) in [("bidder1", 99u128), ("bidder2", 60u128)] {
+        app.execute_contract(
+            Addr::unchecked(bidder),
+            contract_addr.clone(),
+            &BidExecuteMsg::Retract {
+                auction_id: 0,
+                receiver: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(
+            app.wrap()
+                .query_all_balances(Addr::unchecked(bidder))
+                .unwrap(),
+            coins(balance, "atom")
+        );
+    }
+}
+
+#[test]
+fn test_second_price_settlement() {
+    let mut app = App::new(|router, _api, storage| {
+        for bidder in ["bidder1", "bidder2", "bidder3"] {
+            router
+                .bank
+                .init_balance(storage, &Addr::unchecked(bidder), coins(100, "atom"))
+                .unwrap();
+        }
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &BidInstantiateMsg {
+                settlement: Settlement::SecondPrice {},
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    // bidder2 raising his own bid does not change the runner-up
+    for (bidder, amount) in [
+        ("bidder1", 10u128),
+        ("bidder2", 20u128),
+        ("bidder2", 5u128),
+        ("bidder3", 30u128),
+    ] {
+        app.execute_contract(
+            Addr::unchecked(bidder),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(amount, "atom"),
+        )
+        .unwrap();
+    }
+
+    let resp: Uint128 = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetRunnerUpBid { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert_eq!(resp, Uint128::from(25u128));
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    // 4 commissions + second price 25 - 1 commission already paid by bidder3
+    assert_eq!(
+        app.wrap()
+            .query_all_balances(Addr::unchecked("owner"))
+            .unwrap(),
+        coins(28, "atom")
+    );
+
+    // Winner retracts the difference between his bid and the second price
+    app.execute_contract(
+        Addr::unchecked("bidder3"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Retract {
+            auction_id: 0,
+            receiver: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(
+        app.wrap()
+            .query_all_balances(Addr::unchecked("bidder3"))
+            .unwrap(),
+        coins(75, "atom")
+    );
+
+    // Nothing left for the winner
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder3"),
+            contract_addr,
+            &BidExecuteMsg::Retract {
+                auction_id: 0,
+                receiver: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::NothingToRetract {}, err.downcast().unwrap());
+}
+
+#[test]
+fn test_dutch_auction() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(100, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    // Price falls from 100 to 50 over 100 seconds
+    let end = app.block_info().time.plus_seconds(100);
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &BidInstantiateMsg {
+                end: Expiration::AtTime(end),
+                mode: AuctionMode::Dutch {
+                    start_price: Uint128::from(100u128),
+                    floor_price: Uint128::from(50u128),
+                    decline: PriceDecline::Linear {},
+                },
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    app.update_block(|block| block.time = block.time.plus_seconds(40));
+
+    let resp: Uint128 = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetCurrentPrice { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert_eq!(resp, Uint128::from(80u128));
+
+    // No regular bid in a dutch auction
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(90, "atom"),
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::WrongAuctionMode {}, err.downcast().unwrap());
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::BuyNow { auction_id: 0 },
+            &coins(70, "atom"),
+        )
+        .unwrap_err();
+
+    assert_eq!(
+        BidError::PaymentUnderPrice {
+            price: Uint128::from(80u128)
+        },
+        err.downcast().unwrap()
+    );
+
+    // Overpayment is sent back right away
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::BuyNow { auction_id: 0 },
+        &coins(90, "atom"),
+    )
+    .unwrap();
+
+    assert_eq!(
+        app.wrap()
+            .query_all_balances(Addr::unchecked("bidder1"))
+            .unwrap(),
+        coins(20, "atom")
+    );
+    assert_eq!(
+        app.wrap()
+            .query_all_balances(Addr::unchecked("owner"))
+            .unwrap(),
+        coins(80, "atom")
+    );
+
+    let resp: WinningBidderResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetWinningBidder { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert_eq!(
+        resp,
+        WinningBidderResponse::Winner {
+            address: Addr::unchecked("bidder1")
+        }
+    );
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr,
+            &BidExecuteMsg::BuyNow { auction_id: 0 },
+            &coins(20, "atom"),
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::BiddingClosed {}, err.downcast().unwrap());
+}
+
+#[test]
+fn test_buy_now_price() {
+    let mut app = App::new(|router, _api, storage| {
+        for bidder in ["bidder1", "bidder2"] {
+            router
+                .bank
+                .init_balance(storage, &Addr::unchecked(bidder), coins(100, "atom"))
+                .unwrap();
+        }
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    // Buy-now at 50, disabled once the highest bid reaches 40
+    let buy_now_msg = BidInstantiateMsg {
+        buy_now_price: Some(BuyNowPrice {
+            price: Uint128::from(50u128),
+            disable_at: Some(Decimal::percent(80)),
+        }),
+        ..default_instantiate_msg()
+    };
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &buy_now_msg,
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    let resp: Option<Uint128> = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetBuyNowPrice { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert_eq!(resp, Some(Uint128::from(50u128)));
+
+    // Reaching the buy-now price closes the auction and refunds the excess
+    app.execute_contract(
+        Addr::unchecked("bidder2"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(60, "atom"),
+    )
+    .unwrap();
+
+    let resp: WinningBidderResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetWinningBidder { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert_eq!(
+        resp,
+        WinningBidderResponse::Winner {
+            address: Addr::unchecked("bidder2")
+        }
+    );
+    assert_eq!(
+        app.wrap()
+            .query_all_balances(Addr::unchecked("bidder2"))
+            .unwrap(),
+        coins(50, "atom")
+    );
+    // 2 commissions + 50 - 1 commission already paid by bidder2
+    assert_eq!(
+        app.wrap()
+            .query_all_balances(Addr::unchecked("owner"))
+            .unwrap(),
+        coins(51, "atom")
+    );
+
+    let resp: Option<Uint128> = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr,
+            &BidQueryMsg::GetBuyNowPrice { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert_eq!(resp, None);
+
+    // Once the highest bid passes 80% of the buy-now price, bidding goes on as usual
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &buy_now_msg,
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    for (bidder, amount) in [("bidder1", 40u128), ("bidder2", 45u128)] {
+        app.execute_contract(
+            Addr::unchecked(bidder),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(amount, "atom"),
+        )
+        .unwrap();
+    }
+
+    let resp: Option<Uint128> = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetBuyNowPrice { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert_eq!(resp, None);
+
+    let resp: bool = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr,
+            &BidQueryMsg::IsBiddingClosed { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert!(!resp);
+}
+
+#[test]
+fn test_nft_lot() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(100, "atom"))
+            .unwrap();
+    });
+
+    let cw721_id = app.store_code(cw721_contract());
+    let contract_id = app.store_code(bidding_contract());
+
+    let nft_addr = app
+        .instantiate_contract(
+            cw721_id,
+            Addr::unchecked("minter"),
+            &cw721_base::InstantiateMsg {
+                name: "Collection".to_string(),
+                symbol: "COL".to_string(),
+                minter: "minter".to_string(),
+            },
+            &[],
+            "Cw721 Contract",
+            None,
+        )
+        .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("minter"),
+        nft_addr.clone(),
+        &cw721_base::ExecuteMsg::<cw721_base::Extension, Empty>::Mint(cw721_base::MintMsg {
+            token_id: "1".to_string(),
+            owner: "owner".to_string(),
+            token_uri: None,
+            extension: None,
+        }),
+        &[],
+    )
+    .unwrap();
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &BidInstantiateMsg {
+                lot: Some(LotMsg::Nft {
+                    contract: nft_addr.to_string(),
+                    token_id: "1".to_string(),
+                }),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    // No bid until the lot is in escrow
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(10, "atom"),
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::LotNotDeposited {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        nft_addr.clone(),
+        &cw721_base::ExecuteMsg::<cw721_base::Extension, Empty>::SendNft {
+            contract: contract_addr.to_string(),
+            token_id: "1".to_string(),
+            msg: to_binary(&BidReceiveMsg::DepositLot { auction_id: 0 }).unwrap(),
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr,
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    // NFT goes to the winner, the bid to the owner
+    let resp: cw721::OwnerOfResponse = app
+        .wrap()
+        .query_wasm_smart(
+            nft_addr,
+            &cw721_base::QueryMsg::<Empty>::OwnerOf {
+                token_id: "1".to_string(),
+                include_expired: None,
+            },
+        )
+        .unwrap();
+
+    assert_eq!(resp.owner, "bidder1");
+    assert_eq!(
+        app.wrap()
+            .query_all_balances(Addr::unchecked("owner"))
+            .unwrap(),
+        coins(10, "atom")
+    );
+}
+
+#[test]
+fn test_cw20_bidding_currency() {
+    let mut app = App::default();
+
+    let cw20_id = app.store_code(cw20_contract());
+    let contract_id = app.store_code(bidding_contract());
+
+    let token_addr = app
+        .instantiate_contract(
+            cw20_id,
+            Addr::unchecked("minter"),
+            &cw20_base::msg::InstantiateMsg {
+                name: "Stable".to_string(),
+                symbol: "STABLE".to_string(),
+                decimals: 6,
+                initial_balances: vec![
+                    Cw20Coin {
+                        address: "bidder1".to_string(),
+                        amount: Uint128::from(100u128),
+                    },
+                    Cw20Coin {
+                        address: "bidder2".to_string(),
+                        amount: Uint128::from(100u128),
+                    },
+                ],
+                mint: None,
+                marketing: None,
+            },
+            &[],
+            "Cw20 Contract",
+            None,
+        )
+        .unwrap();
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &BidInstantiateMsg {
+                accepted_token: Denom::Cw20(token_addr.clone()),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    let resp: Denom = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetAcceptedDenom { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert_eq!(Denom::Cw20(token_addr.clone()), resp);
+
+    // Native funds are refused
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &[],
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::WrongToken {}, err.downcast().unwrap());
+
+    for (bidder, amount) in [("bidder1", 10u128), ("bidder2", 20u128)] {
+        app.execute_contract(
+            Addr::unchecked(bidder),
+            token_addr.clone(),
+            &Cw20ExecuteMsg::Send {
+                contract: contract_addr.to_string(),
+                amount: Uint128::from(amount),
+                msg: to_binary(&BidReceiveMsg::Bid { auction_id: 0 }).unwrap(),
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr,
+        &BidExecuteMsg::Retract {
+            auction_id: 0,
+            receiver: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // Commissions and the winning bid are paid in the cw20 token
+    for (address, balance) in [("owner", 21u128), ("bidder1", 99u128), ("bidder2", 80u128)] {
+        let resp: BalanceResponse = app
+            .wrap()
+            .query_wasm_smart(
+                token_addr.clone(),
+                &Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(resp.balance, Uint128::from(balance));
+    }
+}
+
+#[test]
+fn test_fungible_token_lot() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(100, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("owner"), coins(500, "gov"))
+            .unwrap();
+    });
+
+    let cw20_id = app.store_code(cw20_contract());
+    let contract_id = app.store_code(bidding_contract());
+
+    let token_addr = app
+        .instantiate_contract(
+            cw20_id,
+            Addr::unchecked("minter"),
+            &cw20_base::msg::InstantiateMsg {
+                name: "Governance".to_string(),
+                symbol: "GOV".to_string(),
+                decimals: 6,
+                initial_balances: vec![Cw20Coin {
+                    address: "owner".to_string(),
+                    amount: Uint128::from(10000u128),
+                }],
+                mint: None,
+                marketing: None,
+            },
+            &[],
+            "Cw20 Contract",
+            None,
+        )
+        .unwrap();
+
+    // Cw20 lot deposited through the receive hook
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &BidInstantiateMsg {
+                lot: Some(LotMsg::Cw20 {
+                    contract: token_addr.to_string(),
+                    amount: Uint128::from(10000u128),
+                }),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(10, "atom"),
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::LotNotDeposited {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        token_addr.clone(),
+        &Cw20ExecuteMsg::Send {
+            contract: contract_addr.to_string(),
+            amount: Uint128::from(10000u128),
+            msg: to_binary(&BidReceiveMsg::DepositLot { auction_id: 0 }).unwrap(),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let resp: LotResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetLot { auction_id: 0 },
+        )
+        .unwrap();
+
+    assert_eq!(
+        resp,
+        LotResponse {
+            lot: Some(Lot::Cw20 {
+                contract: token_addr.clone(),
+                amount: Uint128::from(10000u128),
+            }),
+            escrowed: true,
+        }
+    );
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    let resp: BalanceResponse = app
+        .wrap()
+        .query_wasm_smart(
+            token_addr,
+            &Cw20QueryMsg::Balance {
+                address: "bidder1".to_string(),
+            },
+        )
+        .unwrap();
+
+    assert_eq!(resp.balance, Uint128::from(10000u128));
+
+    let resp: LotResponse = app
+        .wrap()
+        .query_wasm_smart(contract_addr, &BidQueryMsg::GetLot { auction_id: 0 })
+        .unwrap();
+
+    assert!(!resp.escrowed);
+
+    // Native lot sent with the instantiation goes back to the owner when unsold
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &BidInstantiateMsg {
+                reserve_price: Some(Uint128::from(50u128)),
+                lot: Some(LotMsg::Native {
+                    coin: coin(500, "gov"),
+                }),
+                ..default_instantiate_msg()
+            },
+            &coins(500, "gov"),
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr,
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("owner"), "gov")
+            .unwrap(),
+        coin(500, "gov")
+    );
+}
+
+#[test]
+fn test_multiple_auctions() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(50, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder2"), coins(50, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &default_instantiate_msg(),
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    // Funds can only come along as the native lot
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::CreateAuction(Box::new(BidInstantiateMsg {
+                owner: None,
+                ..default_instantiate_msg()
+            })),
+            &coins(40, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(BidError::InvalidLot {}, err.downcast().unwrap());
+
+    // A second seller opens another auction in the same contract
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("seller2"),
+            contract_addr.clone(),
+            &BidExecuteMsg::CreateAuction(Box::new(BidInstantiateMsg {
+                owner: None,
+                ..default_instantiate_msg()
+            })),
+            &[],
+        )
+        .unwrap();
+    resp.assert_event(&Event::new("wasm").add_attribute("Auction id", "1"));
+
+    // Bids on each auction are kept apart
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+    app.execute_contract(
+        Addr::unchecked("bidder2"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 1 },
+        &coins(20, "atom"),
+    )
+    .unwrap();
+
+    let resp: HighestBid = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetHighestBid { auction_id: 1 },
+        )
+        .unwrap();
+    assert_eq!(
+        resp,
+        HighestBid {
+            address: Addr::unchecked("bidder2"),
+            bid: Uint128::from(20u128),
+        }
+    );
+
+    // Only the seller of auction 1 can close it
+    let err = app
+        .execute_contract(
+            Addr::unchecked("owner"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Close { auction_id: 1 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        Addr::unchecked("seller2"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 1 },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("seller2"), "atom")
+            .unwrap(),
+        coin(20, "atom")
+    );
+
+    let resp: WinningBidderResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetWinningBidder { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(resp, WinningBidderResponse::NoWinnerYet {});
+
+    // Listing filters
+    let resp: Vec<AuctionResponse> = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::ListAuctions {
+                status: Some(Phase::Bidding {}),
+                seller: None,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        resp.iter().map(|a| a.auction_id).collect::<Vec<_>>(),
+        vec![0]
+    );
+
+    let resp: Vec<AuctionResponse> = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::ListAuctions {
+                status: None,
+                seller: Some("seller2".to_string()),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.len(), 1);
+    assert_eq!(resp[0].auction_id, 1);
+    assert_eq!(resp[0].phase, Phase::Closed {});
+
+    let resp: Vec<AuctionResponse> = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::ListAuctions {
+                status: None,
+                seller: None,
+                start_after: Some(0),
+                limit: Some(1),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        resp.iter().map(|a| a.auction_id).collect::<Vec<_>>(),
+        vec![1]
+    );
+
+    // Unknown auctions are an error, not a panic
+    app.wrap()
+        .query_wasm_smart::<HighestBid>(
+            contract_addr.clone(),
+            &BidQueryMsg::GetHighestBid { auction_id: 7 },
+        )
+        .unwrap_err();
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr,
+            &BidExecuteMsg::Retract {
+                auction_id: 7,
+                receiver: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(matches!(err.downcast().unwrap(), BidError::Std(_)));
+}
+
+// Single auction layout written by the first version of the contract
+#[cw_serde]
+struct LegacyConfig {
+    owner: Addr,
+    commission: Uint128,
+    accepted_token: Coin,
+}
+
+fn legacy_instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: Empty,
+) -> StdResult<Response> {
+    cw2::set_contract_version(deps.storage, "bidding_contract", "0.1.0")?;
+    Item::new("config").save(
+        deps.storage,
+        &LegacyConfig {
+            owner: Addr::unchecked("owner"),
+            commission: Uint128::from(1u128),
+            accepted_token: coin(0, "atom"),
+        },
+    )?;
+    let bidders: Map<Addr, (Coin, u32)> = Map::new("bidders");
+    bidders.save(
+        deps.storage,
+        Addr::unchecked("bidder1"),
+        &(coin(10, "atom"), 1),
+    )?;
+    bidders.save(
+        deps.storage,
+        Addr::unchecked("bidder2"),
+        &(coin(21, "atom"), 2),
+    )?;
+    Item::new("highets_bid").save(
+        deps.storage,
+        &HighestBid {
+            address: Addr::unchecked("bidder2"),
+            bid: Uint128::from(21u128),
+        },
+    )?;
+    Item::new("is_bidding_close").save(deps.storage, &false)?;
+    Ok(Response::new())
+}
+
+// Closed by the legacy contract before anyone bid
+fn legacy_closed_instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: Empty,
+) -> StdResult<Response> {
+    cw2::set_contract_version(deps.storage, "bidding_contract", "0.1.0")?;
+    Item::new("config").save(
+        deps.storage,
+        &LegacyConfig {
+            owner: Addr::unchecked("owner"),
+            commission: Uint128::from(1u128),
+            accepted_token: coin(0, "atom"),
+        },
+    )?;
+    Item::new("highets_bid").save(
+        deps.storage,
+        &HighestBid {
+            address: Addr::unchecked("instantiator"),
+            bid: Uint128::zero(),
+        },
+    )?;
+    Item::new("is_bidding_close").save(deps.storage, &true)?;
+    Item::new("bid_winner").save(deps.storage, &Addr::unchecked("instantiator"))?;
+    Ok(Response::new())
+}
+
+fn legacy_execute(
+    _deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: Empty,
+) -> StdResult<Response> {
+    Ok(Response::new())
+}
+
+fn legacy_query(_deps: Deps, _env: Env, _msg: Empty) -> StdResult<Binary> {
+    Ok(Binary::default())
+}
+
+#[test]
+fn test_migrate_closed_auction_without_bids() {
+    let mut app = App::default();
+
+    let legacy_id = app.store_code(Box::new(ContractWrapper::new(
+        legacy_execute,
+        legacy_closed_instantiate,
+        legacy_query,
+    )));
+    let contract_id = app.store_code(Box::new(
+        ContractWrapper::new(execute, instantiate, query).with_migrate(migrate),
+    ));
+
+    let contract_addr = app
+        .instantiate_contract(
+            legacy_id,
+            Addr::unchecked("instantiator"),
+            &Empty {},
+            &[],
+            "Bidding Contract",
+            Some("admin".to_string()),
+        )
+        .unwrap();
+
+    app.migrate_contract(
+        Addr::unchecked("admin"),
+        contract_addr.clone(),
+        &BidMigrateMsg {},
+        contract_id,
+    )
+    .unwrap();
+
+    let resp: AuctionResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetAuction { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(resp.phase, Phase::Closed {});
+
+    // Nothing was sold, the instantiator did not win
+    let resp: WinningBidderResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr,
+            &BidQueryMsg::GetWinningBidder { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(resp, WinningBidderResponse::NoSale {});
+}
+
+#[test]
+fn test_migrate_single_auction() {
+    let mut app = App::default();
+
+    let legacy_id = app.store_code(Box::new(ContractWrapper::new(
+        legacy_execute,
+        legacy_instantiate,
+        legacy_query,
+    )));
+    let contract_id = app.store_code(Box::new(
+        ContractWrapper::new(execute, instantiate, query).with_migrate(migrate),
+    ));
+
+    let contract_addr = app
+        .instantiate_contract(
+            legacy_id,
+            Addr::unchecked("instantiator"),
+            &Empty {},
+            &[],
+            "Bidding Contract",
+            Some("admin".to_string()),
+        )
+        .unwrap();
+
+    // Bids escrowed by the legacy contract, commissions already sent
+    app.init_modules(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &contract_addr, coins(28, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(15, "atom"))
+            .unwrap();
+    });
+
+    app.migrate_contract(
+        Addr::unchecked("admin"),
+        contract_addr.clone(),
+        &BidMigrateMsg {},
+        contract_id,
+    )
+    .unwrap();
+
+    let resp: AuctionResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetAuction { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(resp.config.owner, Addr::unchecked("owner"));
+    assert_eq!(resp.phase, Phase::Bidding {});
+    assert_eq!(resp.highest_bid.bid, Uint128::from(21u128));
+
+    // The moved auction goes on as before
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(15, "atom"),
+    )
+    .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("bidder2"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Retract {
+            auction_id: 0,
+            receiver: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // 23 from the winning bid and 1 commission of the new bid
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("owner"), "atom")
+            .unwrap(),
+        coin(24, "atom")
+    );
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("bidder2"), "atom")
+            .unwrap(),
+        coin(19, "atom")
+    );
+    assert_eq!(
+        app.wrap().query_balance(contract_addr, "atom").unwrap(),
+        coin(0, "atom")
+    );
+}
+
+#[test]
+fn test_factory() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(50, "atom"))
+            .unwrap();
+    });
+
+    let bidding_id = app.store_code(bidding_contract());
+    let factory_id = app.store_code(Box::new(
+        ContractWrapper::new(factory::execute, factory::instantiate, factory::query)
+            .with_reply(factory::reply),
+    ));
+
+    let factory_addr = app
+        .instantiate_contract(
+            factory_id,
+            Addr::unchecked("owner"),
+            &FactoryInstantiateMsg {
+                owner: None,
+                bidding_code_id: bidding_id,
+            },
+            &[],
+            "Auction Factory",
+            None,
+        )
+        .unwrap();
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("seller1"),
+            factory_addr.clone(),
+            &FactoryExecuteMsg::UpdateCodeId { code_id: 42 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+
+    // Each auction gets its own bidding contract
+    app.execute_contract(
+        Addr::unchecked("seller1"),
+        factory_addr.clone(),
+        &FactoryExecuteMsg::CreateAuction(Box::new(BidInstantiateMsg {
+            owner: None,
+            ..default_instantiate_msg()
+        })),
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        Addr::unchecked("seller2"),
+        factory_addr.clone(),
+        &FactoryExecuteMsg::CreateAuction(Box::new(BidInstantiateMsg {
+            owner: None,
+            accepted_token: Denom::Cw20(Addr::unchecked("token")),
+            ..default_instantiate_msg()
+        })),
+        &[],
+    )
+    .unwrap();
+
+    let resp: Vec<AuctionEntry> = app
+        .wrap()
+        .query_wasm_smart(
+            factory_addr.clone(),
+            &FactoryQueryMsg::ListAuctions {
+                seller: Some("seller1".to_string()),
+                accepted_token: None,
+                status: None,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.len(), 1);
+    let auction_addr = resp[0].address.clone();
+    assert_eq!(resp[0].accepted_token, Denom::Native("atom".to_string()));
+    assert_eq!(resp[0].status, AuctionStatus::Open {});
+
+    let resp: AuctionResponse = app
+        .wrap()
+        .query_wasm_smart(
+            auction_addr.clone(),
+            &BidQueryMsg::GetAuction { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(resp.config.owner, Addr::unchecked("seller1"));
+    assert_eq!(resp.config.factory, Some(factory_addr.clone()));
+
+    // Only registered auctions can report a close
+    let err = app
+        .execute_contract(
+            Addr::unchecked("seller1"),
+            factory_addr.clone(),
+            &FactoryExecuteMsg::AuctionClosed { auction_id: 0 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        auction_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+    app.execute_contract(
+        Addr::unchecked("seller1"),
+        auction_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    // The close was reported to the factory
+    let resp: AuctionEntry = app
+        .wrap()
+        .query_wasm_smart(
+            factory_addr.clone(),
+            &FactoryQueryMsg::GetAuction {
+                address: auction_addr.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.status, AuctionStatus::Closed {});
+
+    let resp: Vec<AuctionEntry> = app
+        .wrap()
+        .query_wasm_smart(
+            factory_addr.clone(),
+            &FactoryQueryMsg::ListAuctions {
+                seller: None,
+                accepted_token: None,
+                status: Some(AuctionStatus::Open {}),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.len(), 1);
+    assert_eq!(resp[0].seller, Addr::unchecked("seller2"));
+
+    let resp: Vec<AuctionEntry> = app
+        .wrap()
+        .query_wasm_smart(
+            factory_addr,
+            &FactoryQueryMsg::ListAuctions {
+                seller: None,
+                accepted_token: Some(Denom::Cw20(Addr::unchecked("token"))),
+                status: None,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.len(), 1);
+    assert_eq!(resp[0].seller, Addr::unchecked("seller2"));
+}
+
+#[test]
+fn test_multi_winner_auction() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(10, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder2"), coins(8, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder3"), coins(15, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let err = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &BidInstantiateMsg {
+                quantity: Some(2),
+                settlement: Settlement::SecondPrice {},
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap_err();
+    assert_eq!(BidError::InvalidAuctionMode {}, err.downcast().unwrap());
+
+    // Two tickets for the two highest bidders
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &BidInstantiateMsg {
+                quantity: Some(2),
+                reserve_price: Some(Uint128::from(5u128)),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    // A ticket is still available, bidding under the highest bid is fine
+    app.execute_contract(
+        Addr::unchecked("bidder2"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(8, "atom"),
+    )
+    .unwrap();
+
+    // Every ticket is taken, a new bid has to beat the lowest winning one
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder3"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(6, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(BidError::BidTooLow {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        Addr::unchecked("bidder3"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(9, "atom"),
+    )
+    .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    let resp: WinningBidderResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetWinningBidder { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(
+        resp,
+        WinningBidderResponse::Winners {
+            addresses: vec![Addr::unchecked("bidder1"), Addr::unchecked("bidder3")]
+        }
+    );
+
+    // 3 commissions, then both winning bids without their commission
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("owner"), "atom")
+            .unwrap(),
+        coin(20, "atom")
+    );
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Retract {
+                auction_id: 0,
+                receiver: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::WinnerCantRetract {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        Addr::unchecked("bidder2"),
+        contract_addr,
+        &BidExecuteMsg::Retract {
+            auction_id: 0,
+            receiver: None,
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("bidder2"), "atom")
+            .unwrap(),
+        coin(7, "atom")
+    );
+}
+
+#[test]
+fn test_uniform_price_auction() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("owner"), coins(100, "gov"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(150, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder2"), coins(80, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder3"), coins(60, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder4"), coins(10, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    // 100 gov tokens for sale
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &BidInstantiateMsg {
+                mode: AuctionMode::Uniform {},
+                lot: Some(LotMsg::Native {
+                    coin: coin(100, "gov"),
+                }),
+                ..default_instantiate_msg()
+            },
+            &coins(100, "gov"),
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(10, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(BidError::WrongAuctionMode {}, err.downcast().unwrap());
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::BatchBid {
+                auction_id: 0,
+                quantity: Uint128::from(101u128),
+                max_price: Uint128::from(1u128),
+            },
+            &coins(101, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(BidError::InvalidBatchBid {}, err.downcast().unwrap());
+
+    for (bidder, quantity, max_price) in [
+        ("bidder1", 50u128, 3u128),
+        ("bidder2", 40, 2),
+        ("bidder3", 30, 2),
+        ("bidder4", 10, 1),
+    ] {
+        app.execute_contract(
+            Addr::unchecked(bidder),
+            contract_addr.clone(),
+            &BidExecuteMsg::BatchBid {
+                auction_id: 0,
+                quantity: Uint128::from(quantity),
+                max_price: Uint128::from(max_price),
+            },
+            &coins(quantity * max_price, "atom"),
+        )
+        .unwrap();
+    }
+
+    // Same price, bidder2 bid first and is filled first
+    let resp: Clearing = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::SimulateClearing { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(
+        resp,
+        Clearing {
+            price: Uint128::from(2u128),
+            sold: Uint128::from(100u128),
+            supply: Uint128::from(100u128),
+            fills: vec![
+                (Addr::unchecked("bidder1"), Uint128::from(50u128)),
+                (Addr::unchecked("bidder2"), Uint128::from(40u128)),
+                (Addr::unchecked("bidder3"), Uint128::from(10u128)),
+            ],
+        }
+    );
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    // 4 commissions, then 200 paid at the clearing price minus the 3 commissions of the winners
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("owner"), "atom")
+            .unwrap(),
+        coin(201, "atom")
+    );
+    for (bidder, units) in [("bidder1", 50u128), ("bidder2", 40), ("bidder3", 10)] {
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(bidder), "gov")
+                .unwrap(),
+            coin(units, "gov")
+        );
+    }
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder2"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Retract {
+                auction_id: 0,
+                receiver: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::WinnerCantRetract {}, err.downcast().unwrap());
+
+    // Difference with the clearing price, or the whole bid without commission for losers
+    for (bidder, refund) in [("bidder1", 50u128), ("bidder3", 40), ("bidder4", 9)] {
+        app.execute_contract(
+            Addr::unchecked(bidder),
+            contract_addr.clone(),
+            &BidExecuteMsg::Retract {
+                auction_id: 0,
+                receiver: None,
+            },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(bidder), "atom")
+                .unwrap(),
+            coin(refund, "atom")
+        );
+    }
+}
+
+#[test]
+fn test_batch_bids_bounded() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("owner"), coins(100, "gov"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(110, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &BidInstantiateMsg {
+                mode: AuctionMode::Uniform {},
+                lot: Some(LotMsg::Native {
+                    coin: coin(100, "gov"),
+                }),
+                ..default_instantiate_msg()
+            },
+            &coins(100, "gov"),
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    let batch_bid = BidExecuteMsg::BatchBid {
+        auction_id: 0,
+        quantity: Uint128::from(1u128),
+        max_price: Uint128::from(2u128),
+    };
+    for _ in 0..50 {
+        app.execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &batch_bid,
+            &coins(2, "atom"),
+        )
+        .unwrap();
+    }
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &batch_bid,
+            &coins(2, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        BidError::TooManyBatchBids { max: 50 },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr,
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("bidder1"), "gov")
+            .unwrap(),
+        coin(50, "gov")
+    );
+}
+
+#[test]
+fn test_reverse_auction() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("owner"), coins(100, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("supplier1"), coins(10, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("supplier2"), coins(10, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    // Budget of 100 escrowed at instantiation
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &BidInstantiateMsg {
+                mode: AuctionMode::Reverse {
+                    bond: Uint128::from(10u128),
+                },
+                lot: Some(LotMsg::Native {
+                    coin: coin(100, "atom"),
+                }),
+                ..default_instantiate_msg()
+            },
+            &coins(100, "atom"),
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("supplier1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(10, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(BidError::WrongAuctionMode {}, err.downcast().unwrap());
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("supplier1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Offer {
+                auction_id: 0,
+                price: Uint128::from(120u128),
+            },
+            &coins(10, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        BidError::OfferTooHigh {
+            maximum: Uint128::from(100u128)
+        },
+        err.downcast().unwrap()
+    );
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("supplier1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Offer {
+                auction_id: 0,
+                price: Uint128::from(90u128),
+            },
+            &coins(5, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        BidError::WrongBond {
+            bond: Uint128::from(10u128)
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        Addr::unchecked("supplier1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Offer {
+            auction_id: 0,
+            price: Uint128::from(90u128),
+        },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    // Offers have to undercut the best one
+    let err = app
+        .execute_contract(
+            Addr::unchecked("supplier2"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Offer {
+                auction_id: 0,
+                price: Uint128::from(90u128),
+            },
+            &coins(10, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        BidError::OfferTooHigh {
+            maximum: Uint128::from(89u128)
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        Addr::unchecked("supplier2"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Offer {
+            auction_id: 0,
+            price: Uint128::from(80u128),
+        },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    // The bond is already posted
+    app.execute_contract(
+        Addr::unchecked("supplier1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Offer {
+            auction_id: 0,
+            price: Uint128::from(70u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let resp: HighestBid = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetHighestBid { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(
+        resp,
+        HighestBid {
+            address: Addr::unchecked("supplier1"),
+            bid: Uint128::from(70u128),
+        }
+    );
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    // Commission of 1 and the 30 left from the budget
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("owner"), "atom")
+            .unwrap(),
+        coin(31, "atom")
+    );
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("supplier1"), "atom")
+            .unwrap(),
+        coin(69, "atom")
+    );
+
+    // Every supplier gets his bond back
+    for supplier in ["supplier1", "supplier2"] {
+        app.execute_contract(
+            Addr::unchecked(supplier),
+            contract_addr.clone(),
+            &BidExecuteMsg::Retract {
+                auction_id: 0,
+                receiver: None,
+            },
+            &[],
+        )
+        .unwrap();
+    }
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("supplier1"), "atom")
+            .unwrap(),
+        coin(79, "atom")
+    );
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("supplier2"), "atom")
+            .unwrap(),
+        coin(10, "atom")
+    );
+    assert_eq!(
+        app.wrap().query_balance(contract_addr, "atom").unwrap(),
+        coin(0, "atom")
+    );
+}
+
+#[test]
+fn test_proxy_bid() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("proxy"), coins(80, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder"), coins(60, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &default_instantiate_msg(),
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("proxy"),
+        contract_addr.clone(),
+        &BidExecuteMsg::ProxyBid {
+            auction_id: 0,
+            max: Uint128::from(50u128),
+        },
+        &coins(50, "atom"),
+    )
+    .unwrap();
+
+    // Only the minimum needed to lead is shown
+    let resp: u128 = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetTotalBidAddr {
+                auction_id: 0,
+                address: Addr::unchecked("proxy"),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp, 1u128);
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("proxy"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(10, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(BidError::ProxyBidActive {}, err.downcast().unwrap());
+
+    // The proxy bid answers automatically
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("bidder"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(20, "atom"),
+        )
+        .unwrap();
+    resp.assert_event(&Event::new("wasm").add_attribute("Outbid by proxy bid", "OK"));
+
+    let resp: HighestBid = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetHighestBid { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(
+        resp,
+        HighestBid {
+            address: Addr::unchecked("proxy"),
+            bid: Uint128::from(21u128),
+        }
+    );
+
+    // Over the maximum, the bidder takes the lead
+    app.execute_contract(
+        Addr::unchecked("bidder"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(40, "atom"),
+    )
+    .unwrap();
+
+    let resp: u128 = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetTotalBidAddr {
+                auction_id: 0,
+                address: Addr::unchecked("proxy"),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp, 50u128);
+
+    // Raising the maximum only outbids by the minimum increment
+    app.execute_contract(
+        Addr::unchecked("proxy"),
+        contract_addr.clone(),
+        &BidExecuteMsg::ProxyBid {
+            auction_id: 0,
+            max: Uint128::from(80u128),
+        },
+        &coins(30, "atom"),
+    )
+    .unwrap();
+
+    let resp: HighestBid = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetHighestBid { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(
+        resp,
+        HighestBid {
+            address: Addr::unchecked("proxy"),
+            bid: Uint128::from(61u128),
+        }
+    );
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    // 4 commissions and the visible bid without the 2 commissions of the winner
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("owner"), "atom")
+            .unwrap(),
+        coin(63, "atom")
+    );
+
+    // Unused escrow goes back to the proxy bidder
+    for (bidder, refund) in [("proxy", 19u128), ("bidder", 58)] {
+        app.execute_contract(
+            Addr::unchecked(bidder),
+            contract_addr.clone(),
+            &BidExecuteMsg::Retract {
+                auction_id: 0,
+                receiver: None,
+            },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(bidder), "atom")
+                .unwrap(),
+            coin(refund, "atom")
+        );
+    }
+}
+
+#[test]
+fn test_withdraw_before_close() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(10, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder2"), coins(20, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder3"), coins(30, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &BidInstantiateMsg {
+                settlement: Settlement::SecondPrice {},
+                withdrawal_penalty: Some(Decimal::percent(10)),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    for (bidder, amount) in [("bidder1", 10u128), ("bidder2", 20), ("bidder3", 30)] {
+        app.execute_contract(
+            Addr::unchecked(bidder),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(amount, "atom"),
+        )
+        .unwrap();
+    }
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder3"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Withdraw { auction_id: 0 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::WinnerCantWithdraw {}, err.downcast().unwrap());
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder4"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Withdraw { auction_id: 0 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::NothingToRetract {}, err.downcast().unwrap());
+
+    // 19 escrowed after commission, 10% of it kept as penalty
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("bidder2"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Withdraw { auction_id: 0 },
+            &[],
+        )
+        .unwrap();
+    resp.assert_event(
+        &Event::new("wasm")
+            .add_attribute("Execute withdraw", "OK")
+            .add_attribute("Address calling", "bidder2")
+            .add_attribute("Penalty", "1"),
+    );
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("bidder2"), "atom")
+            .unwrap(),
+        coin(18, "atom")
+    );
+
+    // The next bid becomes the runner-up
+    let resp: Uint128 = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetRunnerUpBid { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(resp, Uint128::from(10u128));
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    // 3 commissions, the penalty, and the second price without the winner commission
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("owner"), "atom")
+            .unwrap(),
+        coin(13, "atom")
+    );
+
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("bidder3"),
+            contract_addr,
+            &BidExecuteMsg::Retract {
+                auction_id: 0,
+                receiver: None,
+            },
+            &[],
+        )
+        .unwrap();
+    resp.assert_event(&Event::new("wasm").add_attribute("Execute retract", "OK"));
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("bidder3"), "atom")
+            .unwrap(),
+        coin(20, "atom")
+    );
+}
+
+#[test]
+fn test_process_refunds() {
+    let bids = [
+        ("bidder1", 10u128),
+        ("bidder2", 15),
+        ("bidder4", 20),
+        ("bidder3", 30),
+    ];
+    let mut app = App::new(|router, _api, storage| {
+        for (bidder, amount) in bids {
+            router
+                .bank
+                .init_balance(storage, &Addr::unchecked(bidder), coins(amount, "atom"))
+                .unwrap();
+        }
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &default_instantiate_msg(),
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    for (bidder, amount) in bids {
+        app.execute_contract(
+            Addr::unchecked(bidder),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(amount, "atom"),
+        )
+        .unwrap();
+    }
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("anyone"),
+            contract_addr.clone(),
+            &BidExecuteMsg::ProcessRefunds {
+                auction_id: 0,
+                limit: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::BiddingNotClose {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    let resp: u32 = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetPendingRefunds { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(resp, 3);
+
+    // bidder1 and bidder2 first, bidder3 won and is skipped on the next call
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("anyone"),
+            contract_addr.clone(),
+            &BidExecuteMsg::ProcessRefunds {
+                auction_id: 0,
+                limit: Some(2),
+            },
+            &[],
+        )
+        .unwrap();
+    resp.assert_event(
+        &Event::new("wasm")
+            .add_attribute("Execute process refunds", "OK")
+            .add_attribute("Refunds sent", "2"),
+    );
+
+    let resp: u32 = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetPendingRefunds { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(resp, 1);
+
+    // bidder4 does not wait for the batch refund
+    app.execute_contract(
+        Addr::unchecked("bidder4"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Retract {
+            auction_id: 0,
+            receiver: None,
+        },
+        &[],
+    )
+    .unwrap();
+    let resp: u32 = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetPendingRefunds { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(resp, 0);
+
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("anyone"),
+            contract_addr.clone(),
+            &BidExecuteMsg::ProcessRefunds {
+                auction_id: 0,
+                limit: Some(2),
+            },
+            &[],
+        )
+        .unwrap();
+    resp.assert_event(&Event::new("wasm").add_attribute("Refunds sent", "0"));
+
+    for (bidder, refund) in [
+        ("bidder1", 9u128),
+        ("bidder2", 14),
+        ("bidder3", 0),
+        ("bidder4", 19),
+    ] {
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(bidder), "atom")
+                .unwrap(),
+            coin(refund, "atom")
+        );
+    }
+
+    let resp: u32 = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetPendingRefunds { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(resp, 0);
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("anyone"),
+            contract_addr.clone(),
+            &BidExecuteMsg::ProcessRefunds {
+                auction_id: 0,
+                limit: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::NothingToRetract {}, err.downcast().unwrap());
+
+    // Refunded bidders have nothing left to retract
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr,
+            &BidExecuteMsg::Retract {
+                auction_id: 0,
+                receiver: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::NothingToRetract {}, err.downcast().unwrap());
+}
+
+#[test]
+fn test_ownership_transfer() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(10, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder2"), coins(20, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+    let height = app.block_info().height;
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &BidInstantiateMsg {
+                end: Expiration::AtHeight(height + 100),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    // Without deadline, only the owner can close
+    let endless_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &default_instantiate_msg(),
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+    let err = app
+        .execute_contract(
+            Addr::unchecked("owner"),
+            endless_addr,
+            &BidExecuteMsg::RenounceOwnership { auction_id: 0 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        BidError::RenounceWithoutDeadline {},
+        err.downcast().unwrap()
+    );
+
+    let transfer = |new_owner: &str, expiry| BidExecuteMsg::TransferOwnership {
+        auction_id: 0,
+        new_owner: new_owner.to_string(),
+        expiry,
+    };
+    let accept = BidExecuteMsg::AcceptOwnership { auction_id: 0 };
+    let close = BidExecuteMsg::Close { auction_id: 0 };
+
+    // Offer not accepted in time
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &transfer("owner3", Some(Expiration::AtHeight(height + 1))),
+        &[],
+    )
+    .unwrap();
+    app.update_block(|block| block.height += 1);
+    let err = app
+        .execute_contract(
+            Addr::unchecked("owner3"),
+            contract_addr.clone(),
+            &accept,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        BidError::OwnershipTransferExpired {},
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    // A bidder can not take over the auction
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &transfer("bidder1", None),
+        &[],
+    )
+    .unwrap();
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &accept,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::OwnerCantBid {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &transfer("owner2", None),
+        &[],
+    )
+    .unwrap();
+    let err = app
+        .execute_contract(
+            Addr::unchecked("someone"),
+            contract_addr.clone(),
+            &accept,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+
+    let resp: OwnershipResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetOwnership { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(
+        resp,
+        OwnershipResponse {
+            owner: Some(Addr::unchecked("owner")),
+            pending_owner: Some(Addr::unchecked("owner2")),
+            pending_expiry: None,
+        }
+    );
+
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("owner2"),
+            contract_addr.clone(),
+            &accept,
+            &[],
+        )
+        .unwrap();
+    resp.assert_event(&Event::new("wasm").add_attribute("Execute accept ownership", "OK"));
+
+    // Commission now goes to the new owner
+    app.execute_contract(
+        Addr::unchecked("bidder2"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(20, "atom"),
+    )
+    .unwrap();
+
+    let err = app
+        .execute_contract(Addr::unchecked("owner"), contract_addr.clone(), &close, &[])
+        .unwrap_err();
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        Addr::unchecked("owner2"),
+        contract_addr.clone(),
+        &BidExecuteMsg::RenounceOwnership { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    let resp: OwnershipResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetOwnership { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(
+        resp,
+        OwnershipResponse {
+            owner: None,
+            pending_owner: None,
+            pending_expiry: None,
+        }
+    );
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("owner2"),
+            contract_addr.clone(),
+            &close,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+    let err = app
+        .execute_contract(
+            Addr::unchecked("owner2"),
+            contract_addr.clone(),
+            &transfer("owner", None),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+
+    // Anyone closes after the deadline, the proceeds still follow the last owner
+    app.update_block(|block| block.height += 100);
+    app.execute_contract(Addr::unchecked("anyone"), contract_addr, &close, &[])
+        .unwrap();
+
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("owner"), "atom")
+            .unwrap(),
+        coin(1, "atom")
+    );
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("owner2"), "atom")
+            .unwrap(),
+        coin(20, "atom")
+    );
+}
+
+#[test]
+fn test_roles_and_pause() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(10, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &default_instantiate_msg(),
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    let grant = |role, address: &str| BidExecuteMsg::GrantRole {
+        auction_id: 0,
+        role,
+        address: address.to_string(),
+    };
+    let update_fees = BidExecuteMsg::UpdateFeeRecipients {
+        auction_id: 0,
+        recipients: vec![FeeRecipientMsg {
+            address: "treasury".to_string(),
+            share: Decimal::one(),
+        }],
+    };
+    let pause = BidExecuteMsg::Pause { auction_id: 0 };
+    let resume = BidExecuteMsg::Resume { auction_id: 0 };
+    let bid = BidExecuteMsg::Bid { auction_id: 0 };
+
+    // Only the owner or an admin manages roles
+    let err = app
+        .execute_contract(
+            Addr::unchecked("admin"),
+            contract_addr.clone(),
+            &grant(Role::Operator {}, "operator"),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &grant(Role::Admin {}, "admin"),
+        &[],
+    )
+    .unwrap();
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("admin"),
+            contract_addr.clone(),
+            &grant(Role::Operator {}, "operator"),
+            &[],
+        )
+        .unwrap();
+    resp.assert_event(
+        &Event::new("wasm")
+            .add_attribute("Execute grant role", "OK")
+            .add_attribute("Role", "operator")
+            .add_attribute("Address", "operator"),
+    );
+    app.execute_contract(
+        Addr::unchecked("admin"),
+        contract_addr.clone(),
+        &grant(Role::FeeManager {}, "fee_manager"),
+        &[],
+    )
+    .unwrap();
+
+    let resp: Vec<Addr> = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetRoleMembers {
+                auction_id: 0,
+                role: Role::Operator {},
+            },
+        )
+        .unwrap();
+    assert_eq!(resp, vec![Addr::unchecked("operator")]);
+
+    // Each role only gets its own rights
+    let err = app
+        .execute_contract(
+            Addr::unchecked("operator"),
+            contract_addr.clone(),
+            &update_fees,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+    app.execute_contract(
+        Addr::unchecked("fee_manager"),
+        contract_addr.clone(),
+        &update_fees,
+        &[],
+    )
+    .unwrap();
+    let err = app
+        .execute_contract(
+            Addr::unchecked("fee_manager"),
+            contract_addr.clone(),
+            &pause,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        Addr::unchecked("operator"),
+        contract_addr.clone(),
+        &pause,
+        &[],
+    )
+    .unwrap();
+    let resp: bool = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::IsPaused { auction_id: 0 },
+        )
+        .unwrap();
+    assert!(resp);
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &bid,
+            &coins(10, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(BidError::AuctionPaused {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        Addr::unchecked("operator"),
+        contract_addr.clone(),
+        &resume,
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &bid,
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    // Commission follows the fee recipients set by the fee manager
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("treasury"), "atom")
+            .unwrap(),
+        coin(1, "atom")
+    );
+
+    app.execute_contract(
+        Addr::unchecked("admin"),
+        contract_addr.clone(),
+        &BidExecuteMsg::RevokeRole {
+            auction_id: 0,
+            role: Role::Operator {},
+            address: "operator".to_string(),
+        },
+        &[],
+    )
+    .unwrap();
+    let resp: Vec<Addr> = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetRoleMembers {
+                auction_id: 0,
+                role: Role::Operator {},
+            },
+        )
+        .unwrap();
+    assert!(resp.is_empty());
+
+    let close = BidExecuteMsg::Close { auction_id: 0 };
+    let err = app
+        .execute_contract(
+            Addr::unchecked("operator"),
+            contract_addr.clone(),
+            &close,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        Addr::unchecked("admin"),
+        contract_addr.clone(),
+        &grant(Role::Operator {}, "operator2"),
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(Addr::unchecked("operator2"), contract_addr, &close, &[])
+        .unwrap();
+}
+
+#[test]
+fn test_pause_stops_the_clock() {
+    let mut app = App::new(|router, _api, storage| {
+        for bidder in ["bidder1", "bidder2", "operator"] {
+            router
+                .bank
+                .init_balance(storage, &Addr::unchecked(bidder), coins(20, "atom"))
+                .unwrap();
+        }
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+    let end = app.block_info().height + 10;
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &BidInstantiateMsg {
+                end: Expiration::AtHeight(end),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    let bid = BidExecuteMsg::Bid { auction_id: 0 };
+    let close = BidExecuteMsg::Close { auction_id: 0 };
+    let grant_operator = |address: &str| BidExecuteMsg::GrantRole {
+        auction_id: 0,
+        role: Role::Operator {},
+        address: address.to_string(),
+    };
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &bid,
+        &coins(5, "atom"),
+    )
+    .unwrap();
+    app.execute_contract(
+        Addr::unchecked("bidder2"),
+        contract_addr.clone(),
+        &bid,
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    // Bidders can not be granted a role, role holders can not bid
+    let err = app
+        .execute_contract(
+            Addr::unchecked("owner"),
+            contract_addr.clone(),
+            &grant_operator("bidder2"),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::OwnerCantBid {}, err.downcast().unwrap());
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &grant_operator("operator"),
+        &[],
+    )
+    .unwrap();
+    let err = app
+        .execute_contract(
+            Addr::unchecked("operator"),
+            contract_addr.clone(),
+            &bid,
+            &coins(20, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(BidError::OwnerCantBid {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        Addr::unchecked("operator"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Pause { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    // Withdrawals keep working while paused
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Withdraw { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    // The deadline passing while paused does not let anyone close
+    app.update_block(|block| block.height += 20);
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder2"),
+            contract_addr.clone(),
+            &close,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder2"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Resume { auction_id: 0 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+
+    // Nobody would be left to resume or close it
+    let err = app
+        .execute_contract(
+            Addr::unchecked("owner"),
+            contract_addr.clone(),
+            &BidExecuteMsg::RenounceOwnership { auction_id: 0 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::AuctionPaused {}, err.downcast().unwrap());
+
+    // Bidders get the 20 paused blocks back
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("operator"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Resume { auction_id: 0 },
+            &[],
+        )
+        .unwrap();
+    resp.assert_event(
+        &Event::new("wasm")
+            .add_attribute("Execute resume", "OK")
+            .add_attribute("End", Expiration::AtHeight(end + 20).to_string()),
+    );
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &bid,
+        &coins(15, "atom"),
+    )
+    .unwrap();
+
+    // Pausing past the deadline would keep bidders from closing
+    app.update_block(|block| block.height += 10);
+    let err = app
+        .execute_contract(
+            Addr::unchecked("operator"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Pause { auction_id: 0 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::BiddingExpired {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        Addr::unchecked("bidder2"),
+        contract_addr.clone(),
+        &close,
+        &[],
+    )
+    .unwrap();
+    let resp: WinningBidderResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr,
+            &BidQueryMsg::GetWinningBidder { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(
+        resp,
+        WinningBidderResponse::Winner {
+            address: Addr::unchecked("bidder1")
+        }
+    );
+}
+
+#[test]
+fn test_pause_lapses() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(10, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+    let end = app.block_info().height + 10;
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &BidInstantiateMsg {
+                end: Expiration::AtHeight(end),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Pause { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    // After a week of pause anyone can close once the deadline has passed
+    app.update_block(|block| {
+        block.height += 20;
+        block.time = block.time.plus_seconds(7 * 24 * 3600);
+    });
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("owner"), "atom")
+            .unwrap(),
+        coin(10, "atom")
+    );
+}
+
+#[test]
+fn test_pause_lapsed_resume() {
+    let mut app = App::default();
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &default_instantiate_msg(),
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Pause { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    // After a week of pause anyone can resume
+    app.update_block(|block| block.time = block.time.plus_seconds(7 * 24 * 3600));
+    app.execute_contract(
+        Addr::unchecked("someone"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Resume { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+    let resp: bool = app
+        .wrap()
+        .query_wasm_smart(contract_addr, &BidQueryMsg::IsPaused { auction_id: 0 })
+        .unwrap();
+    assert!(!resp);
+}
+
+#[test]
+fn test_update_config() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(20, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+    let height = app.block_info().height;
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &BidInstantiateMsg {
+                end: Expiration::AtHeight(height + 100),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    let update_config = |update| BidExecuteMsg::UpdateConfig {
+        auction_id: 0,
+        update,
+    };
+    let flat = |amount: u128| Commission::FlatPerBid {
+        amount: Uint128::from(amount),
+    };
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("someone"),
+            contract_addr.clone(),
+            &update_config(ConfigUpdate {
+                commission: Some(flat(2)),
+                ..ConfigUpdate::default()
+            }),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+    let err = app
+        .execute_contract(
+            Addr::unchecked("someone"),
+            contract_addr.clone(),
+            &update_config(ConfigUpdate::default()),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+    let err = app
+        .execute_contract(
+            Addr::unchecked("owner"),
+            contract_addr.clone(),
+            &update_config(ConfigUpdate::default()),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::EmptyConfigUpdate {}, err.downcast().unwrap());
+
+    // Anything goes before the first bid
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("owner"),
+            contract_addr.clone(),
+            &update_config(ConfigUpdate {
+                commission: Some(flat(2)),
+                ..ConfigUpdate::default()
+            }),
+            &[],
+        )
+        .unwrap();
+    resp.assert_event(
+        &Event::new("wasm-update_config")
+            .add_attribute("setting", "commission")
+            .add_attribute("old", r#"{"flat_per_bid":{"amount":"1"}}"#)
+            .add_attribute("new", r#"{"flat_per_bid":{"amount":"2"}}"#),
+    );
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("owner"), "atom")
+            .unwrap(),
+        coin(2, "atom")
+    );
+
+    for (update, setting) in [
+        (
+            ConfigUpdate {
+                commission: Some(flat(3)),
+                ..ConfigUpdate::default()
+            },
+            "commission",
+        ),
+        (
+            ConfigUpdate {
+                accepted_token: Some(Denom::Native("osmo".to_string())),
+                ..ConfigUpdate::default()
+            },
+            "accepted_token",
+        ),
+        (
+            ConfigUpdate {
+                end: Some(Expiration::AtHeight(height + 50)),
+                ..ConfigUpdate::default()
+            },
+            "end",
+        ),
+        (
+            ConfigUpdate {
+                reserve_price: Some(Uint128::from(50u128)),
+                ..ConfigUpdate::default()
+            },
+            "reserve_price",
+        ),
+    ] {
+        let err = app
+            .execute_contract(
+                Addr::unchecked("owner"),
+                contract_addr.clone(),
+                &update_config(update),
+                &[],
+            )
+            .unwrap_err();
+        assert_eq!(
+            BidError::ConfigLocked {
+                setting: setting.to_string()
+            },
+            err.downcast().unwrap()
+        );
+    }
+
+    // Changes in favour of the bidders are still accepted
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("owner"),
+            contract_addr.clone(),
+            &update_config(ConfigUpdate {
+                commission: Some(flat(1)),
+                end: Some(Expiration::AtHeight(height + 200)),
+                ..ConfigUpdate::default()
+            }),
+            &[],
+        )
+        .unwrap();
+    resp.assert_event(
+        &Event::new("wasm-update_config")
+            .add_attribute("setting", "end")
+            .add_attribute("old", format!(r#"{{"at_height":{}}}"#, height + 100))
+            .add_attribute("new", format!(r#"{{"at_height":{}}}"#, height + 200)),
+    );
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("owner"), "atom")
+            .unwrap(),
+        coin(3, "atom")
+    );
+
+    let resp: BiddingWindowResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr,
+            &BidQueryMsg::GetBiddingWindow { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(resp.end, Expiration::AtHeight(height + 200));
+}
+
+#[test]
+fn test_timelocked_config_changes() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(10, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder2"), coins(20, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+    let now = app.block_info().time;
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &BidInstantiateMsg {
+                config_timelock: Some(Duration::Time(48 * 3600)),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    let update_config = |update| BidExecuteMsg::UpdateConfig {
+        auction_id: 0,
+        update,
+    };
+    let raise_commission = ConfigUpdate {
+        commission: Some(Commission::FlatPerBid {
+            amount: Uint128::from(2u128),
+        }),
+        ..ConfigUpdate::default()
+    };
+    let treasury = ConfigUpdate {
+        fee_recipients: Some(vec![FeeRecipientMsg {
+            address: "treasury".to_string(),
+            share: Decimal::one(),
+        }]),
+        ..ConfigUpdate::default()
+    };
+
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("owner"),
+            contract_addr.clone(),
+            &update_config(raise_commission.clone()),
+            &[],
+        )
+        .unwrap();
+    resp.assert_event(
+        &Event::new("wasm")
+            .add_attribute("Execute update config", "OK")
+            .add_attribute("Change id", "0"),
+    );
+    for update in [
+        treasury.clone(),
+        ConfigUpdate {
+            reserve_price: Some(Uint128::from(5u128)),
+            ..ConfigUpdate::default()
+        },
+    ] {
+        app.execute_contract(
+            Addr::unchecked("owner"),
+            contract_addr.clone(),
+            &update_config(update),
+            &[],
+        )
+        .unwrap();
+    }
+
+    let cancel = BidExecuteMsg::CancelConfigChange {
+        auction_id: 0,
+        change_id: 2,
+    };
+    let err = app
+        .execute_contract(
+            Addr::unchecked("someone"),
+            contract_addr.clone(),
+            &cancel,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::Unauthorized {}, err.downcast().unwrap());
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &cancel,
+        &[],
+    )
+    .unwrap();
+    let err = app
+        .execute_contract(
+            Addr::unchecked("owner"),
+            contract_addr.clone(),
+            &cancel,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::ConfigChangeNotFound {}, err.downcast().unwrap());
+
+    let effective = Expiration::AtTime(now.plus_seconds(48 * 3600));
+    let resp: Vec<PendingConfigChange> = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetPendingConfigChanges { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(
+        resp,
+        vec![
+            PendingConfigChange {
+                id: 0,
+                update: raise_commission,
+                effective,
+            },
+            PendingConfigChange {
+                id: 1,
+                update: treasury.clone(),
+                effective,
+            },
+        ]
+    );
+
+    // Nothing changes before the timelock has passed
+    app.update_block(|block| block.time = now.plus_seconds(24 * 3600));
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("owner"), "atom")
+            .unwrap(),
+        coin(1, "atom")
+    );
+
+    // The raise would now hurt bidder1 and is dropped, the new recipient applies to this bid
+    app.update_block(|block| block.time = now.plus_seconds(48 * 3600));
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("bidder2"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(20, "atom"),
+        )
+        .unwrap();
+    resp.assert_event(&Event::new("wasm-config_change_dropped").add_attribute("change_id", "0"));
+    resp.assert_event(&Event::new("wasm-update_config").add_attribute("setting", "fee_recipients"));
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("treasury"), "atom")
+            .unwrap(),
+        coin(1, "atom")
+    );
+
+    let resp: Vec<PendingConfigChange> = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetPendingConfigChanges { auction_id: 0 },
+        )
+        .unwrap();
+    assert!(resp.is_empty());
+
+    // Fee recipients go through the timelock too, any bidder action applies them
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("owner"),
+            contract_addr.clone(),
+            &BidExecuteMsg::UpdateFeeRecipients {
+                auction_id: 0,
+                recipients: vec![FeeRecipientMsg {
+                    address: "owner".to_string(),
+                    share: Decimal::one(),
+                }],
+            },
+            &[],
+        )
+        .unwrap();
+    resp.assert_event(
+        &Event::new("wasm")
+            .add_attribute("Execute update config", "OK")
+            .add_attribute("Change id", "3"),
+    );
+    app.update_block(|block| block.time = now.plus_seconds(96 * 3600));
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr.clone(),
+            &BidExecuteMsg::Withdraw { auction_id: 0 },
+            &[],
+        )
+        .unwrap();
+    resp.assert_event(&Event::new("wasm-update_config").add_attribute("setting", "fee_recipients"));
+
+    // The queue is bounded
+    for _ in 0..10 {
+        app.execute_contract(
+            Addr::unchecked("owner"),
+            contract_addr.clone(),
+            &update_config(treasury.clone()),
+            &[],
+        )
+        .unwrap();
+    }
+    let err = app
+        .execute_contract(
+            Addr::unchecked("owner"),
+            contract_addr,
+            &update_config(treasury),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        BidError::TooManyPendingConfigChanges { max: 10 },
+        err.downcast().unwrap()
+    );
+}
+
+#[test]
+fn test_dutch_price_under_commission() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(10, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let end = app.block_info().time.plus_seconds(100);
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &BidInstantiateMsg {
+                commission: Commission::FlatPerBid {
+                    amount: Uint128::from(10u128),
+                },
+                end: Expiration::AtTime(end),
+                mode: AuctionMode::Dutch {
+                    start_price: Uint128::from(8u128),
+                    floor_price: Uint128::from(5u128),
+                    decline: PriceDecline::Linear {},
+                },
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    // The current price does not even cover the commission
+    let err = app
+        .execute_contract(
+            Addr::unchecked("bidder1"),
+            contract_addr,
+            &BidExecuteMsg::BuyNow { auction_id: 0 },
+            &coins(10, "atom"),
+        )
+        .unwrap_err();
+
+    assert_eq!(BidError::BidUnderCommission {}, err.downcast().unwrap());
+}
+
+#[test]
+fn test_failing_factory_notification() {
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked("bidder1"), coins(10, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    // Another bidding contract does not understand AuctionClosed
+    let not_a_factory = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &default_instantiate_msg(),
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    // The factory has to be the instantiator
+    let err = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("instantiator"),
+            &BidInstantiateMsg {
+                factory: Some(not_a_factory.to_string()),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap_err();
+    assert_eq!(BidError::InvalidFactory {}, err.downcast().unwrap());
+    let err = app
+        .execute_contract(
+            Addr::unchecked("instantiator"),
+            not_a_factory.clone(),
+            &BidExecuteMsg::CreateAuction(Box::new(BidInstantiateMsg {
+                factory: Some("instantiator".to_string()),
+                ..default_instantiate_msg()
+            })),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(BidError::InvalidFactory {}, err.downcast().unwrap());
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            not_a_factory.clone(),
+            &BidInstantiateMsg {
+                factory: Some(not_a_factory.to_string()),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    app.execute_contract(
+        Addr::unchecked("bidder1"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Bid { auction_id: 0 },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    let resp = app
+        .execute_contract(
+            Addr::unchecked("owner"),
+            contract_addr,
+            &BidExecuteMsg::Close { auction_id: 0 },
+            &[],
+        )
+        .unwrap();
+    resp.assert_event(&Event::new("wasm").add_attribute("Execute close with funds to owner", "OK"));
+    assert!(resp.events.iter().any(|event| event
+        .attributes
+        .iter()
+        .any(|attribute| attribute.key == "Factory notification failed")));
+
+    assert_eq!(
+        app.wrap()
+            .query_balance(Addr::unchecked("owner"), "atom")
+            .unwrap(),
+        coin(10, "atom")
+    );
+}
+
+#[test]
+fn test_multi_winner_tie() {
+    let mut app = App::new(|router, _api, storage| {
+        for (bidder, amount) in [("bidder1", 10u128), ("bidder2", 10), ("bidder3", 20)] {
+            router
+                .bank
+                .init_balance(storage, &Addr::unchecked(bidder), coins(amount, "atom"))
+                .unwrap();
+        }
+    });
+
+    let contract_id = app.store_code(bidding_contract());
+
+    let contract_addr = app
+        .instantiate_contract(
+            contract_id,
+            Addr::unchecked("owner"),
+            &BidInstantiateMsg {
+                quantity: Some(2),
+                ..default_instantiate_msg()
+            },
+            &[],
+            "Bidding Contract",
+            None,
+        )
+        .unwrap();
+
+    for (bidder, amount) in [("bidder1", 10u128), ("bidder2", 10), ("bidder3", 20)] {
+        app.execute_contract(
+            Addr::unchecked(bidder),
+            contract_addr.clone(),
+            &BidExecuteMsg::Bid { auction_id: 0 },
+            &coins(amount, "atom"),
+        )
+        .unwrap();
+    }
+
+    // Ranking among the winners only takes the lowest winning bid, not the highest one
+    let resp: Uint128 = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr.clone(),
+            &BidQueryMsg::GetMinimumNextBid {
+                auction_id: 0,
+                bidder: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(resp, Uint128::from(11u128));
+
+    app.execute_contract(
+        Addr::unchecked("owner"),
+        contract_addr.clone(),
+        &BidExecuteMsg::Close { auction_id: 0 },
+        &[],
+    )
+    .unwrap();
+
+    // bidder1 reached 10 first and keeps the last item
+    let resp: WinningBidderResponse = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr,
+            &BidQueryMsg::GetWinningBidder { auction_id: 0 },
+        )
+        .unwrap();
+    assert_eq!(
+        resp,
+        WinningBidderResponse::Winners {
+            addresses: vec![Addr::unchecked("bidder3"), Addr::unchecked("bidder1")]
+        }
+    );
 }
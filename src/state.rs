@@ -1,14 +1,381 @@
+use crate::msg::ConfigUpdate;
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Coin, Uint128};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{
+    to_binary, Addr, BankMsg, Binary, BlockInfo, Coin, CosmosMsg, Decimal, Empty, StdResult,
+    Uint128, WasmMsg,
+};
+use cw20::{Cw20ExecuteMsg, Denom};
+use cw721::Cw721ExecuteMsg;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, KeyDeserialize, Map, MultiIndex};
+use cw_utils::{Duration, Expiration, Scheduled};
 
 #[cw_serde]
 pub struct Config {
     pub owner: Addr,
-    pub commission: Uint128,
-    pub accepted_token: Coin,
+    pub commission: Commission,
+    // Native denom or cw20 contract bids are paid with
+    pub accepted_token: Denom,
+    // None means bidding is open right after instantiation
+    pub start: Option<Scheduled>,
+    // Expiration::Never keeps the auction open until the owner closes it
+    pub end: Expiration,
+    pub anti_sniping: Option<AntiSniping>,
+    // Highest bid under this price at close ends the auction without sale
+    pub reserve_price: Option<Uint128>,
+    pub min_increment: Option<MinIncrement>,
+    // Empty means the owner gets the whole commission
+    pub fee_recipients: Vec<FeeRecipient>,
+    pub mode: AuctionMode,
+    pub settlement: Settlement,
+    pub buy_now_price: Option<BuyNowPrice>,
+    // None when nothing is escrowed, the winner is only recorded
+    pub lot: Option<Lot>,
+    // Registry updated when the auction closes
+    pub factory: Option<Addr>,
+    // Identical items sold, one to each of the `quantity` highest bidders
+    pub quantity: u32,
+    // Share of the escrow kept as commission on early withdrawals
+    pub withdrawal_penalty: Option<Decimal>,
+    // Delay between a config update and its effect
+    pub config_timelock: Option<Duration>,
+}
+
+impl Config {
+    // Deadline once the anti-sniping extensions already granted are applied
+    pub fn extended_end(&self, extensions: u32) -> StdResult<Expiration> {
+        match &self.anti_sniping {
+            Some(anti_sniping) => self.end + anti_sniping.extension * u64::from(extensions),
+            None => Ok(self.end),
+        }
+    }
+
+    // Smallest total bid that can outbid the current highest one
+    pub fn minimum_next_bid(&self, highest_bid: Uint128) -> Uint128 {
+        highest_bid + self.increment(highest_bid)
+    }
+
+    // Proxy bidding : visible bid needed to lead over `to_beat`, at least the reserve price and
+    // never over the maximum escrowed
+    pub fn proxy_visible_bid(&self, max: Uint128, to_beat: Uint128) -> Uint128 {
+        self.minimum_next_bid(to_beat)
+            .max(self.reserve_price.unwrap_or_default())
+            .min(max)
+    }
+
+    // Reverse mode : largest offer that undercuts the current best one
+    pub fn maximum_next_offer(&self, best_offer: Uint128) -> Uint128 {
+        best_offer.saturating_sub(self.increment(best_offer))
+    }
+
+    // Smallest step between two bids, at least 1
+    fn increment(&self, reference: Uint128) -> Uint128 {
+        let mut increment = Uint128::one();
+        if let Some(min_increment) = &self.min_increment {
+            if let Some(absolute) = min_increment.absolute {
+                increment = increment.max(absolute);
+            }
+            if let Some(percentage) = min_increment.percentage {
+                increment = increment.max(reference * percentage);
+            }
+        }
+        increment
+    }
+
+    // Share of a commission for each recipient, rounding dust goes to the first one
+    pub fn split_commission(&self, commission: Uint128) -> Vec<(Addr, Uint128)> {
+        if self.fee_recipients.is_empty() {
+            return vec![(self.owner.clone(), commission)];
+        }
+
+        let mut split: Vec<(Addr, Uint128)> = self
+            .fee_recipients
+            .iter()
+            .map(|recipient| (recipient.address.clone(), commission * recipient.share))
+            .collect();
+        let distributed = split
+            .iter()
+            .fold(Uint128::zero(), |total, (_, amount)| total + *amount);
+        split[0].1 += commission - distributed;
+
+        split
+    }
+
+    // Price paid by the winner, before taking into account commissions he already paid
+    pub fn settlement_price(&self, highest_bid: Uint128, runner_up_bid: Uint128) -> Uint128 {
+        match self.settlement {
+            Settlement::FirstPrice {} => highest_bid,
+            Settlement::SecondPrice {} => runner_up_bid.max(self.reserve_price.unwrap_or_default()),
+        }
+    }
+
+    // Buy-now price still on offer, None once the highest bid went past its disable threshold
+    pub fn active_buy_now_price(&self, highest_bid: Uint128) -> Option<Uint128> {
+        let buy_now_price = self.buy_now_price.as_ref()?;
+        match buy_now_price.disable_at {
+            Some(disable_at) if highest_bid >= buy_now_price.price * disable_at => None,
+            _ => Some(buy_now_price.price),
+        }
+    }
+
+    // Dutch mode : asking price at `block`, None for other modes
+    pub fn dutch_price(&self, block: &BlockInfo) -> Option<Uint128> {
+        let (start_price, floor_price, decline) = match &self.mode {
+            AuctionMode::Dutch {
+                start_price,
+                floor_price,
+                decline,
+            } => (start_price, floor_price, decline),
+            _ => return None,
+        };
+
+        // Start is always resolved to a time when a dutch auction is created
+        let start = match self.start {
+            Some(Scheduled::AtTime(start)) => start.seconds(),
+            _ => return Some(*start_price),
+        };
+        let elapsed = block.time.seconds().saturating_sub(start);
+
+        let price = match decline {
+            PriceDecline::Linear {} => match self.end {
+                Expiration::AtTime(end) if block.time.seconds() < end.seconds() => {
+                    let duration = end.seconds() - start;
+                    *start_price - (*start_price - *floor_price).multiply_ratio(elapsed, duration)
+                }
+                _ => *floor_price,
+            },
+            PriceDecline::Stepwise { step, interval } => {
+                let steps = Uint128::from(elapsed / interval);
+                start_price.saturating_sub(step.saturating_mul(steps))
+            }
+        };
+
+        Some(price.max(*floor_price))
+    }
+
+    // Phase reached at `block` coming from the last stored one
+    pub fn phase_at(&self, stored: Phase, extensions: u32, block: &BlockInfo) -> StdResult<Phase> {
+        if stored == (Phase::Closed {}) {
+            return Ok(Phase::Closed {});
+        }
+        if let Some(start) = self.start {
+            if !start.is_triggered(block) {
+                return Ok(Phase::NotStarted {});
+            }
+        }
+        if !self.extended_end(extensions)?.is_expired(block) {
+            return Ok(match self.mode {
+                AuctionMode::Open {}
+                | AuctionMode::Dutch { .. }
+                | AuctionMode::Uniform {}
+                | AuctionMode::Reverse { .. } => Phase::Bidding {},
+                AuctionMode::Sealed { .. } => Phase::Commit {},
+            });
+        }
+        if let AuctionMode::Sealed { reveal_end, .. } = self.mode {
+            if !reveal_end.is_expired(block) {
+                return Ok(Phase::Reveal {});
+            }
+        }
+        Ok(Phase::Ended {})
+    }
+}
+pub const CONFIG: Map<u64, Config> = Map::new("auction_config");
+
+#[cw_serde]
+pub enum Commission {
+    // Fixed amount paid on every bid
+    FlatPerBid { amount: Uint128 },
+    // Share of the winning bid, paid at close by the winner only
+    PercentOfWinningBid { rate: Decimal },
+    // Share of every bid, paid when the bid is placed
+    PercentOfEachBid { rate: Decimal },
+}
+
+impl Commission {
+    // Commission paid when a bid is placed
+    pub fn on_bid(&self, bid: Uint128) -> Uint128 {
+        match self {
+            Commission::FlatPerBid { amount } => *amount,
+            Commission::PercentOfEachBid { rate } => bid * *rate,
+            Commission::PercentOfWinningBid { .. } => Uint128::zero(),
+        }
+    }
+
+    // Whether bidders pay at most what they pay with `other`, which has to be of the same kind
+    pub fn is_at_most(&self, other: &Commission) -> bool {
+        match (self, other) {
+            (Commission::FlatPerBid { amount }, Commission::FlatPerBid { amount: other }) => {
+                amount <= other
+            }
+            (
+                Commission::PercentOfWinningBid { rate },
+                Commission::PercentOfWinningBid { rate: other },
+            )
+            | (
+                Commission::PercentOfEachBid { rate },
+                Commission::PercentOfEachBid { rate: other },
+            ) => rate <= other,
+            _ => false,
+        }
+    }
+
+    // Commission paid at close on the winning bid
+    pub fn on_winning_bid(&self, bid: Uint128) -> Uint128 {
+        match self {
+            Commission::PercentOfWinningBid { rate } => bid * *rate,
+            _ => Uint128::zero(),
+        }
+    }
+}
+
+#[cw_serde]
+pub enum AuctionMode {
+    // Every bid is public and has to outbid the highest one
+    Open {},
+    // Bidders commit a hash with a deposit until the end, then reveal until reveal_end.
+    // Unrevealed deposits are sent to the fee recipients when slashed, refunded otherwise
+    Sealed {
+        reveal_end: Expiration,
+        slash_unrevealed: bool,
+    },
+    // Price falls from start_price to floor_price until someone buys at the current price
+    Dutch {
+        start_price: Uint128,
+        floor_price: Uint128,
+        decline: PriceDecline,
+    },
+    // Units of a fungible lot sold at a single clearing price. Every bid asks for a quantity at a
+    // maximum unit price, the lowest price still getting units is paid by every winner
+    Uniform {},
+    // Procurement : the owner escrows a budget as lot, suppliers post `bond` and offer decreasing
+    // prices, the lowest offer is paid from the budget
+    Reverse {
+        bond: Uint128,
+    },
+}
+
+#[cw_serde]
+pub enum PriceDecline {
+    // Straight line from the start to the end of the auction
+    Linear {},
+    // Drops by `step` every `interval` seconds
+    Stepwise { step: Uint128, interval: u64 },
+}
+
+#[cw_serde]
+pub enum Settlement {
+    // Winner pays his own bid
+    FirstPrice {},
+    // Winner pays the second highest bid, or the reserve price if higher
+    SecondPrice {},
+}
+
+#[cw_serde]
+pub enum Phase {
+    NotStarted {},
+    // Open auction accepting bids
+    Bidding {},
+    // Sealed auction accepting commitments
+    Commit {},
+    // Sealed auction accepting reveals
+    Reveal {},
+    // Deadline passed, waiting for close
+    Ended {},
+    Closed {},
+}
+
+#[cw_serde]
+pub struct FeeRecipient {
+    pub address: Addr,
+    pub share: Decimal,
+}
+
+// When both are set, a new bid has to satisfy the largest of the two
+#[cw_serde]
+pub struct MinIncrement {
+    pub absolute: Option<Uint128>,
+    pub percentage: Option<Decimal>,
+}
+
+// A new highest bid landing less than `window` before the deadline pushes it back by `extension`,
+// at most `max_extensions` times
+#[cw_serde]
+pub struct AntiSniping {
+    pub window: Duration,
+    pub extension: Duration,
+    pub max_extensions: u32,
+}
+
+// A bid bringing the bidder total to `price` wins right away. Disabled once the highest bid reaches
+// `disable_at` times the price
+#[cw_serde]
+pub struct BuyNowPrice {
+    pub price: Uint128,
+    pub disable_at: Option<Decimal>,
+}
+
+// Sold by the auction, deposited by the owner before bidding can start. A native lot can also be
+// sent along the instantiation
+#[cw_serde]
+pub enum Lot {
+    Nft { contract: Addr, token_id: String },
+    Native { coin: Coin },
+    Cw20 { contract: Addr, amount: Uint128 },
+}
+
+impl Lot {
+    // Sends the escrowed lot to `recipient`
+    pub fn transfer_msg(&self, recipient: &Addr) -> StdResult<CosmosMsg> {
+        match self {
+            Lot::Nft { contract, token_id } => Ok(WasmMsg::Execute {
+                contract_addr: contract.to_string(),
+                msg: to_binary(&Cw721ExecuteMsg::TransferNft {
+                    recipient: recipient.to_string(),
+                    token_id: token_id.clone(),
+                })?,
+                funds: vec![],
+            }
+            .into()),
+            Lot::Native { coin } => Ok(BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![coin.clone()],
+            }
+            .into()),
+            Lot::Cw20 { contract, amount } => Ok(WasmMsg::Execute {
+                contract_addr: contract.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: recipient.to_string(),
+                    amount: *amount,
+                })?,
+                funds: vec![],
+            }
+            .into()),
+        }
+    }
+
+    // Fungible lots : number of units
+    pub fn units(&self) -> Option<Uint128> {
+        match self {
+            Lot::Nft { .. } => None,
+            Lot::Native { coin } => Some(coin.amount),
+            Lot::Cw20 { amount, .. } => Some(*amount),
+        }
+    }
+
+    // Fungible lots : same token, `units` of it
+    pub fn with_units(&self, units: Uint128) -> Option<Lot> {
+        match self {
+            Lot::Nft { .. } => None,
+            Lot::Native { coin } => Some(Lot::Native {
+                coin: Coin::new(units.u128(), coin.denom.clone()),
+            }),
+            Lot::Cw20 { contract, .. } => Some(Lot::Cw20 {
+                contract: contract.clone(),
+                amount: units,
+            }),
+        }
+    }
 }
-pub const CONFIG: Item<Config> = Item::new("config");
 
 #[cw_serde]
 pub struct HighestBid {
@@ -16,9 +383,176 @@ pub struct HighestBid {
     pub bid: Uint128,
 }
 
-// the u32 represent how many time the user bidded
-pub const BIDDERS: Map<Addr, (Coin, u32)> = Map::new("bidders");
-pub const HIGHEST_BID: Item<HighestBid> = Item::new("highets_bid");
+#[cw_serde]
+#[derive(Default)]
+pub struct Bidder {
+    // Everything sent by the bidder, commissions included
+    pub amount: Uint128,
+    pub nb_bids: u32,
+    // Part of amount already sent as commission, never refunded
+    pub commission_paid: Uint128,
+    // Proxy bidding : bid shown to others, the amount escrowed being the maximum
+    pub proxy_bid: Option<Uint128>,
+    // Taken when the amount was last raised, the earliest ranks first among equal amounts
+    pub sequence: u64,
+}
+
+// Amount and reversed sequence, the earliest bidder comes first among equal amounts
+pub type BidRank = (u128, u64);
+
+pub struct BidderIndexes<'a> {
+    // (auction id, rank) to rank the bidders of an auction
+    pub amount: MultiIndex<'a, (u64, BidRank), Bidder, (u64, Addr)>,
+}
+
+impl<'a> IndexList<Bidder> for BidderIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Bidder>> + '_> {
+        let v: Vec<&dyn Index<Bidder>> = vec![&self.amount];
+        Box::new(v.into_iter())
+    }
+}
+
+// Every auction state below is keyed by its auction id
+pub fn bidders<'a>() -> IndexedMap<'a, (u64, Addr), Bidder, BidderIndexes<'a>> {
+    let indexes = BidderIndexes {
+        amount: MultiIndex::new(
+            |pk, bidder| {
+                // Keys are always written by the contract
+                let (auction_id, _) = <(u64, Addr)>::from_slice(pk).unwrap();
+                (
+                    auction_id,
+                    (bidder.amount.u128(), u64::MAX - bidder.sequence),
+                )
+            },
+            "auction_bidders",
+            "auction_bidders__amount",
+        ),
+    };
+    IndexedMap::new("auction_bidders", indexes)
+}
+// sequence given to the next bidder raising his amount
+pub const NEXT_BID_SEQUENCE: Map<u64, u64> = Map::new("auction_next_bid_sequence");
+
+pub const HIGHEST_BID: Map<u64, HighestBid> = Map::new("auction_highest_bid");
+// highest bid of anyone but the current highest bidder
+pub const RUNNER_UP_BID: Map<u64, Uint128> = Map::new("auction_runner_up_bid");
+
+// Last phase saved, time driven transitions are applied when loaded through Config::phase_at
+pub const PHASE: Map<u64, Phase> = Map::new("auction_phase");
+
+// Sealed mode : commitment of every bidder who did not reveal yet
+pub const COMMITMENTS: Map<(u64, Addr), Binary> = Map::new("auction_commitments");
+// Sealed mode : deposits not revealed yet, commissions excluded
+pub const UNREVEALED_DEPOSITS: Map<u64, Uint128> = Map::new("auction_unrevealed_deposits");
+// Sealed mode : bidders who did not reveal yet, missing until the first commitment
+pub const UNREVEALED_BIDDERS: Map<u64, u32> = Map::new("auction_unrevealed_bidders");
+
+#[cw_serde]
+pub enum AuctionOutcome {
+    Sold { winner: Addr },
+    // Multi-winner mode : every bidder ranked among the top `quantity` bought one item
+    SoldToMany { winners: Vec<Addr> },
+    NoSale {},
+}
+
+impl AuctionOutcome {
+    pub fn is_winner(&self, address: &Addr) -> bool {
+        match self {
+            AuctionOutcome::Sold { winner } => winner == address,
+            AuctionOutcome::SoldToMany { winners } => winners.contains(address),
+            AuctionOutcome::NoSale {} => false,
+        }
+    }
+}
+
+pub const AUCTION_OUTCOME: Map<u64, AuctionOutcome> = Map::new("auction_outcome");
+// last bidder processed by the batch refund after close
+pub const REFUND_CURSOR: Map<u64, Addr> = Map::new("auction_refund_cursor");
+// bidders escrowing, only counted until close. Missing until the first bid
+pub const BIDDER_COUNT: Map<u64, u32> = Map::new("auction_bidder_count");
+// losers the batch refund still has to go through, counted on first use after close
+pub const PENDING_REFUNDS: Map<u64, u32> = Map::new("auction_pending_refunds");
+
+// Uniform mode : bid asking for `quantity` units at `max_price` each at most
+#[cw_serde]
+pub struct BatchBid {
+    pub bidder: Addr,
+    pub quantity: Uint128,
+    pub max_price: Uint128,
+}
+
+// Uniform mode : bids keyed by their placement order, which breaks ties between equal prices
+pub const BATCH_BIDS: Map<(u64, u64), BatchBid> = Map::new("auction_batch_bids");
+pub const NEXT_BATCH_BID_ID: Map<u64, u64> = Map::new("auction_next_batch_bid_id");
+
+// Uniform mode : result of the auction if it closed now
+#[cw_serde]
+pub struct Clearing {
+    pub price: Uint128,
+    pub sold: Uint128,
+    pub supply: Uint128,
+    // Units won by every winner, in the order bids are filled
+    pub fills: Vec<(Addr, Uint128)>,
+}
+
+// Two-step transfer of an auction, the owner keeps his payouts once renounced but loses his rights
+#[cw_serde]
+#[derive(Default)]
+pub struct Ownership {
+    pub pending_owner: Option<Addr>,
+    // None when the offer does not expire
+    pub pending_expiry: Option<Expiration>,
+    pub renounced: bool,
+}
+
+// missing until the owner first transfers or renounces
+pub const OWNERSHIP: Map<u64, Ownership> = Map::new("auction_ownership");
+
+// Config update waiting for its timelock
+#[cw_serde]
+pub struct PendingConfigChange {
+    pub id: u64,
+    pub update: ConfigUpdate,
+    pub effective: Expiration,
+}
+
+pub const PENDING_CONFIG_CHANGES: Map<(u64, u64), PendingConfigChange> =
+    Map::new("auction_pending_config_changes");
+// missing until the first update is queued
+pub const NEXT_CONFIG_CHANGE_ID: Map<u64, u64> = Map::new("auction_next_config_change_id");
+
+// Delegated rights, the owner holds all of them until he renounces
+#[cw_serde]
+pub enum Role {
+    // Grants and revokes roles
+    Admin {},
+    // Closes, pauses and resumes the auction
+    Operator {},
+    // Changes the commission settings
+    FeeManager {},
+}
+
+impl Role {
+    pub fn key(&self) -> &'static str {
+        match self {
+            Role::Admin {} => "admin",
+            Role::Operator {} => "operator",
+            Role::FeeManager {} => "fee_manager",
+        }
+    }
+}
+
+pub const ROLES: Map<(u64, &str, Addr), Empty> = Map::new("auction_roles");
+
+// Block bidding was suspended at, missing while the auction runs. The deadline is pushed back by
+// the paused time on resume
+pub const PAUSED: Map<u64, BlockInfo> = Map::new("auction_paused");
+
+// whether the lot is currently held by the contract
+pub const LOT_DEPOSITED: Map<u64, bool> = Map::new("auction_lot_deposited");
+
+// how many times the deadline has been extended by anti-sniping
+pub const EXTENSIONS: Map<u64, u32> = Map::new("auction_extensions");
 
-pub const IS_BIDDING_CLOSED: Item<bool> = Item::new("is_bidding_close");
-pub const BID_WINNER: Item<Addr> = Item::new("bid_winner");
+// id given to the next auction created, the one created at instantiation is 0
+pub const NEXT_AUCTION_ID: Item<u64> = Item::new("next_auction_id");